@@ -1,42 +1,387 @@
+use crate::clipboard::{Clipboard, ClipboardProvider};
+use crate::editor::{Indent, Position};
+use crate::encoding::{self, Encoding, LineEnding};
+use crate::file_ops;
+use crate::finder::{Finder, FinderResult};
 use crate::highlight::Highlighter;
+use crate::history::History;
+use crate::merge;
+use crate::pane::{self, Pane, Panes};
+use crate::prompt::{Confirm, ConfirmAction, Prompt, PromptAction, PromptResult};
+use crate::search::{Search, SearchResult};
+use crate::session::{ExplorerSession, Session, SessionStore, TabSession};
+use crate::swap::{self, SwapFile, SwapStore};
+use crate::watcher::FileWatcher;
 use crate::{editor::Editor, file_explorer::FileExplorer};
-use anyhow::Result;
-use crossterm::event::KeyEvent;
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Direction, Rect};
 use ropey::Rope;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How many closed tabs can be reopened.
+const CLOSED_TABS_LIMIT: usize = 20;
+/// How often the contents of modified tabs are written to swap files.
+const SWAP_INTERVAL: Duration = Duration::from_secs(4);
 
 /// The main application state for the TUI code editor.
 pub struct App {
+    pub buffers: Buffers,
     pub tabs: Vec<Tab>,
     pub current_tab: usize,
     pub file_explorer: FileExplorer,
     pub show_file_explorer: bool,
+    /// Editor panes; the focused one shows the current tab.
+    pub panes: Panes,
+    pub focus: Focus,
     pub show_help: bool,
     pub status_message: Option<String>,
     pub status_timer: u64,
     pub highlighter: Highlighter,
+    pub clipboard: Clipboard,
+    pub areas: ScreenAreas,
+    /// Index of the first tab shown in the tab bar when not all tabs fit.
+    pub tab_scroll: usize,
+    /// Whether a left-button drag started in the editor and is extending the selection.
+    pub mouse_selecting: bool,
+    /// Text input popup, which receives all keys while open.
+    pub prompt: Option<Prompt>,
+    /// Question popup, which receives all keys while open.
+    pub confirm: Option<Confirm>,
+    /// Fuzzy file finder overlay, which receives all keys while open.
+    pub finder: Option<Finder>,
+    /// Search bar of the current buffer, which receives all keys while open.
+    pub search: Option<Search>,
+    /// The last closed search, whose options the next one starts with.
+    last_search: Option<Search>,
+    /// Set when the editor should exit after the current event.
+    pub should_quit: bool,
+    /// What to do once the current tab is saved, e.g. when an untitled tab is saved
+    /// through the Save As prompt before quitting.
+    after_save: Option<AfterSave>,
+    /// Files of recently closed tabs, most recent last, for reopening them.
+    closed_tabs: Vec<ClosedTab>,
+    /// Indentation settings given to every tab's editor.
+    indent: Indent,
+    /// Where to keep the previous version of files when saving.
+    pub backup: file_ops::Backup,
+    /// Where modified tabs are saved for crash recovery; disabled if unset.
+    pub swap: Option<SwapStore>,
+    /// Where the open tabs are remembered between launches; disabled if unset.
+    pub sessions: Option<SessionStore>,
+    last_swap_write: Instant,
+    /// Number given to the next untitled tab, naming its swap file.
+    next_untitled: usize,
+    /// Swap files left by an earlier session, waiting to be offered for recovery.
+    recoveries: Vec<SwapFile>,
+    /// Reports changes to open files; without it the files are checked on every tick.
+    watcher: Option<FileWatcher>,
+    /// A change on disk could not be handled yet because a popup was open.
+    disk_check_pending: bool,
 }
 
-pub struct Tab {
+/// Action waiting for the current tab to be saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AfterSave {
+    /// Save the remaining modified tabs, then quit.
+    Quit,
+    /// Close the tab of the saved buffer.
+    Close(BufferId),
+}
+
+/// A closed tab, remembered so it can be reopened.
+#[derive(Debug, Clone)]
+struct ClosedTab {
+    path: PathBuf,
+    cursor: Position,
+    scroll_offset: Position,
+}
+
+/// Which panel receives key input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Editor,
+    Explorer,
+}
+
+/// Screen areas from the last drawn frame, used to map mouse positions.
+#[derive(Debug, Default, Clone)]
+pub struct ScreenAreas {
+    pub tabs: Rect,
+    /// Horizontal extent `(start, end)` of each tab title; empty for tabs scrolled out of
+    /// view.
+    pub tab_titles: Vec<(u16, u16)>,
+    pub explorer: Option<Rect>,
+    pub editor: Rect,
+    /// The editor's text area, excluding borders and line numbers.
+    pub editor_text: Rect,
+    /// Every editor pane; `editor` and `editor_text` are those of the focused one.
+    pub panes: Vec<PaneArea>,
+}
+
+/// Screen area of an editor pane.
+#[derive(Debug, Clone, Copy)]
+pub struct PaneArea {
+    pub id: usize,
+    pub area: Rect,
+    /// The text area, excluding borders and line numbers.
+    pub text: Rect,
+}
+
+/// Identifies a [`Buffer`] in [`Buffers`].
+pub type BufferId = usize;
+
+/// The text of an open file or untitled document with its undo history and state on disk.
+/// Tabs and panes are views onto buffers, so edits made through one show in all of them.
+pub struct Buffer {
     pub path: Option<PathBuf>,
     pub content: Rope,
-    pub editor: Editor,
+    pub history: History,
     pub modified: bool,
     pub name: String,
+    /// The file was deleted from the explorer while open.
+    pub orphaned: bool,
+    /// The file does not exist yet; the first save creates it.
+    pub is_new: bool,
+    /// Name of the syntax used for highlighting.
+    pub syntax: String,
+    /// Encoding the file is read and written with.
+    pub encoding: Encoding,
+    /// Line ending the file is written with; the buffer itself always uses `\n`.
+    pub line_ending: LineEnding,
+    /// Encoding and line ending of the file as last saved or loaded.
+    saved_format: (Encoding, LineEnding),
+    /// The file as loaded mixes line endings, so saving it changes some of them.
+    mixed_line_endings: bool,
+    /// The file on disk as last loaded or saved, to notice changes made by other programs.
+    disk: Option<DiskState>,
+    /// Content as last loaded or saved, the common ancestor when merging changes on disk.
+    base_text: String,
+    /// Number of an untitled buffer, naming its swap file.
+    untitled_id: usize,
+    /// Name and content hash of the swap file last written for this buffer.
+    swap: Option<(String, u64)>,
+}
+
+/// A tab: a view with its own cursor and scroll position onto a buffer.
+pub struct Tab {
+    pub buffer: BufferId,
+    pub editor: Editor,
+}
+
+/// The open buffers, looked up by id or by the canonical path of their file so that a
+/// file is only ever loaded into one buffer.
+#[derive(Default)]
+pub struct Buffers {
+    buffers: BTreeMap<BufferId, Buffer>,
+    by_path: HashMap<PathBuf, BufferId>,
+    next_id: BufferId,
+}
+
+/// Modification time, size and content hash of a file on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiskState {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl DiskState {
+    /// The state of the file at `path`, which currently contains `bytes`.
+    fn new(path: &Path, bytes: &[u8]) -> Self {
+        let metadata = std::fs::metadata(path).ok();
+        Self {
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            len: bytes.len() as u64,
+            hash: hash_bytes(bytes),
+        }
+    }
+
+    /// Whether the metadata still matches, meaning the file is most likely unchanged.
+    fn matches(&self, metadata: &std::fs::Metadata) -> bool {
+        self.modified == metadata.modified().ok() && self.len == metadata.len()
+    }
+}
+
+impl Buffer {
+    fn new(path: Option<PathBuf>, content: Rope, highlighter: &Highlighter) -> Self {
+        let name = path.as_deref().map_or("Untitled".to_string(), file_name);
+        let syntax = highlighter.syntax_name_for(path.as_deref());
+        Self {
+            path,
+            content,
+            history: History::new(),
+            modified: false,
+            name,
+            orphaned: false,
+            is_new: false,
+            syntax,
+            encoding: Encoding::default(),
+            line_ending: LineEnding::default(),
+            saved_format: (Encoding::default(), LineEnding::default()),
+            mixed_line_endings: false,
+            disk: None,
+            base_text: String::new(),
+            untitled_id: 0,
+            swap: None,
+        }
+    }
+
+    /// Replace the whole content with `text` as a single undo step. Views onto the buffer
+    /// need [`App::clamp_views`] afterwards.
+    fn replace_all(&mut self, text: &str) {
+        Editor::new().replace_all(text, &mut self.content, &mut self.history);
+    }
+
+    /// Take the file contents `bytes` read from disk as the new content, as one undo step
+    /// that is marked as saved.
+    fn reload(&mut self, bytes: &[u8]) {
+        let (text, encoding) = Encoding::decode(bytes);
+        let line_ending = LineEnding::detect(&text);
+        self.mixed_line_endings = LineEnding::is_mixed(&text);
+        let text = LineEnding::normalize(&text);
+        self.replace_all(&text);
+        self.history.mark_saved();
+        self.encoding = encoding;
+        self.line_ending = line_ending;
+        self.saved_format = (encoding, line_ending);
+        self.base_text = text;
+        self.update_modified();
+    }
+
+    /// Recompute `modified` from the undo history and the file format.
+    fn update_modified(&mut self) {
+        self.modified =
+            self.history.is_modified() || (self.encoding, self.line_ending) != self.saved_format;
+    }
+
+    /// Warning to append to a message about loading the file, if saving it would change
+    /// some of its line endings.
+    fn line_ending_warning(&self) -> String {
+        if self.mixed_line_endings {
+            format!(
+                "; mixed line endings will be saved as {}",
+                self.line_ending.name()
+            )
+        } else {
+            String::new()
+        }
+    }
+}
+
+impl Tab {
+    fn new(buffer: BufferId, indent: Indent) -> Self {
+        let mut editor = Editor::new();
+        editor.indent = indent;
+        Self { buffer, editor }
+    }
+}
+
+impl Buffers {
+    pub fn get(&self, id: BufferId) -> Option<&Buffer> {
+        self.buffers.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: BufferId) -> Option<&mut Buffer> {
+        self.buffers.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BufferId, &Buffer)> {
+        self.buffers.iter().map(|(&id, buffer)| (id, buffer))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (BufferId, &mut Buffer)> {
+        self.buffers.iter_mut().map(|(&id, buffer)| (id, buffer))
+    }
+
+    /// The buffer of the file at `path`, if it is open.
+    pub fn find(&self, path: &Path) -> Option<BufferId> {
+        self.by_path.get(&file_ops::absolute(path)).copied()
+    }
+
+    fn insert(&mut self, buffer: Buffer) -> BufferId {
+        let id = self.next_id;
+        self.next_id += 1;
+        if let Some(path) = &buffer.path {
+            self.by_path.insert(file_ops::absolute(path), id);
+        }
+        self.buffers.insert(id, buffer);
+        id
+    }
+
+    fn remove(&mut self, id: BufferId) -> Option<Buffer> {
+        let buffer = self.buffers.remove(&id)?;
+        self.by_path.retain(|_, other| *other != id);
+        Some(buffer)
+    }
+
+    /// Point buffer `id` at a new path, e.g. after the file was renamed or saved under a
+    /// new name.
+    fn set_path(&mut self, id: BufferId, path: PathBuf, highlighter: &Highlighter) {
+        let Some(buffer) = self.buffers.get_mut(&id) else {
+            return;
+        };
+        self.by_path.retain(|_, other| *other != id);
+        self.by_path.insert(file_ops::absolute(&path), id);
+        buffer.name = file_name(&path);
+        buffer.syntax = highlighter.syntax_name_for(Some(&path));
+        buffer.path = Some(path);
+    }
+}
+
+impl std::ops::Index<BufferId> for Buffers {
+    type Output = Buffer;
+
+    fn index(&self, id: BufferId) -> &Buffer {
+        &self.buffers[&id]
+    }
+}
+
+impl std::ops::IndexMut<BufferId> for Buffers {
+    fn index_mut(&mut self, id: BufferId) -> &mut Buffer {
+        self.buffers.get_mut(&id).expect("no buffer with this id")
+    }
 }
 
 impl App {
     /// Create a new App with an initial empty tab and file explorer.
     pub fn new() -> Result<Self> {
         let mut app = Self {
+            buffers: Buffers::default(),
             tabs: Vec::new(),
             current_tab: 0,
             file_explorer: FileExplorer::new()?,
             show_file_explorer: true,
+            panes: Panes::new(),
+            focus: Focus::Editor,
             show_help: false,
             status_message: None,
             status_timer: 0,
             highlighter: Highlighter::new(),
+            clipboard: Clipboard::new(ClipboardProvider::Internal),
+            areas: ScreenAreas::default(),
+            tab_scroll: 0,
+            mouse_selecting: false,
+            prompt: None,
+            confirm: None,
+            finder: None,
+            search: None,
+            last_search: None,
+            should_quit: false,
+            after_save: None,
+            closed_tabs: Vec::new(),
+            indent: Indent::default(),
+            backup: file_ops::Backup::None,
+            swap: None,
+            sessions: None,
+            last_swap_write: Instant::now(),
+            next_untitled: 0,
+            recoveries: Vec::new(),
+            watcher: FileWatcher::new().ok(),
+            disk_check_pending: false,
         };
 
         // Create initial empty tab
@@ -44,103 +389,2376 @@ impl App {
         Ok(app)
     }
 
+    /// Use `indent` for Tab/Shift+Tab in all open and future tabs.
+    pub fn set_indent(&mut self, indent: Indent) {
+        self.indent = indent;
+        for tab in &mut self.tabs {
+            tab.editor.indent = indent;
+        }
+    }
+
     /// Create a new empty file tab.
     pub fn new_file(&mut self) {
-        let tab = Tab {
-            path: None,
-            content: Rope::from(""),
-            editor: Editor::new(),
-            modified: false,
-            name: "Untitled".to_string(),
-        };
-        self.tabs.push(tab);
-        self.current_tab = self.tabs.len() - 1;
+        let mut buffer = Buffer::new(None, Rope::new(), &self.highlighter);
+        buffer.untitled_id = self.next_untitled;
+        self.next_untitled += 1;
+        let id = self.buffers.insert(buffer);
+        self.push_tab(id);
         self.set_status_message("New file created".to_string());
     }
 
-    /// Open a file in a new tab.
+    /// Open a new tab onto buffer `id` and make it current.
+    fn push_tab(&mut self, id: BufferId) {
+        self.tabs.push(Tab::new(id, self.indent));
+        self.current_tab = self.tabs.len() - 1;
+    }
+
+    /// Open a file in a new tab, or switch to a tab showing it if it is already open. A path
+    /// that does not exist yet opens an empty tab marked as new, and binary files are only
+    /// opened after confirmation.
     pub fn open_file<P: Into<PathBuf>>(&mut self, path: P) -> Result<()> {
-        let path = path.into();
-        let content = std::fs::read_to_string(&path).unwrap_or_default();
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Untitled")
-            .to_string();
-        let tab = Tab {
-            path: Some(path.clone()),
-            content: Rope::from(content),
-            editor: Editor::new(),
-            modified: false,
-            name,
+        self.load_file(path.into(), false)
+    }
+
+    fn load_file(&mut self, path: PathBuf, allow_binary: bool) -> Result<()> {
+        if path.is_dir() {
+            bail!("{} is a directory", path.display());
+        }
+        if let Some(id) = self.buffers.find(&path) {
+            match self.tabs.iter().position(|tab| tab.buffer == id) {
+                Some(index) => self.current_tab = index,
+                None => self.push_tab(id),
+            }
+            self.set_status_message(format!("Switched to {}", path.display()));
+            return Ok(());
+        }
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut buffer = Buffer::new(Some(path.clone()), Rope::new(), &self.highlighter);
+                buffer.is_new = true;
+                let id = self.buffers.insert(buffer);
+                self.push_tab(id);
+                self.set_status_message(format!("New file: {}", path.display()));
+                self.queue_leftover_swap(&path);
+                return Ok(());
+            }
+            Err(e) => return Err(e).with_context(|| format!("Cannot read {}", path.display())),
         };
-        self.tabs.push(tab);
-        self.current_tab = self.tabs.len() - 1;
-        self.set_status_message(format!("Opened file: {}", path.display()));
+        if !allow_binary && encoding::is_binary(&bytes) {
+            self.confirm = Some(Confirm::yes_no(
+                format!(
+                    "{} looks like a binary file. Open it anyway?",
+                    file_name(&path)
+                ),
+                ConfirmAction::OpenBinary { path },
+            ));
+            return Ok(());
+        }
+
+        let (text, encoding) = Encoding::decode(&bytes);
+        let line_ending = LineEnding::detect(&text);
+        let mut buffer = Buffer::new(
+            Some(path.clone()),
+            Rope::from(LineEnding::normalize(&text)),
+            &self.highlighter,
+        );
+        buffer.encoding = encoding;
+        buffer.line_ending = line_ending;
+        buffer.saved_format = (encoding, line_ending);
+        buffer.mixed_line_endings = LineEnding::is_mixed(&text);
+        buffer.disk = Some(DiskState::new(&path, &bytes));
+        let message = format!(
+            "Opened file: {}{}",
+            path.display(),
+            buffer.line_ending_warning()
+        );
+        buffer.base_text = LineEnding::normalize(&text);
+        buffer.update_modified();
+        let id = self.buffers.insert(buffer);
+        self.push_tab(id);
+        self.set_status_message(message);
+        self.queue_leftover_swap(&path);
         Ok(())
     }
 
-    /// Save the currently open file.
+    /// Show the fuzzy file finder for the explorer's directory.
+    pub fn open_finder(&mut self) {
+        self.finder = Some(Finder::new(self.file_explorer.current_path.clone()));
+    }
+
+    /// Handle a key while the file finder is open.
+    pub fn handle_finder_input(&mut self, key: KeyEvent) {
+        let Some(finder) = &mut self.finder else {
+            return;
+        };
+        match finder.handle_key(key) {
+            FinderResult::Pending => {}
+            FinderResult::Cancel => self.finder = None,
+            FinderResult::Open(path) => {
+                self.finder = None;
+                self.focus = Focus::Editor;
+                if let Err(e) = self.open_file(path) {
+                    self.set_status_message(format!("Error opening file: {e}"));
+                }
+            }
+        }
+    }
+
+    /// Show the search bar for the current buffer, searching for the selected text if
+    /// there is a selection within a line.
+    pub fn open_search(&mut self) {
+        let Some(tab) = self.tabs.get(self.current_tab) else {
+            return;
+        };
+        let content = &self.buffers[tab.buffer].content;
+        let mut search = Search::new(tab.buffer, tab.editor.get_char_index(content));
+        if let Some(last) = &self.last_search {
+            search.case_sensitive = last.case_sensitive;
+            search.whole_word = last.whole_word;
+        }
+        if let Some(text) = tab
+            .editor
+            .selected_text(content)
+            .filter(|text| !text.contains('\n'))
+        {
+            search.query = text;
+            search.origin = tab
+                .editor
+                .selection_range(content)
+                .map_or(search.origin, |(start, _)| start);
+            search.update(content, search.origin);
+        }
+        self.search = Some(search);
+        self.focus = Focus::Editor;
+    }
+
+    /// Handle a key while the search bar is open: typing searches as you type, moving to
+    /// the first match after the cursor, and the current match is selected.
+    pub fn handle_search_input(&mut self, key: KeyEvent) {
+        if let Some(search) = &mut self.search {
+            let result = search.handle_key(key);
+            self.apply_search_result(result);
+        }
+    }
+
+    fn apply_search_result(&mut self, result: SearchResult) {
+        let (Some(search), Some(tab)) = (&mut self.search, self.tabs.get_mut(self.current_tab))
+        else {
+            return;
+        };
+        let content = &self.buffers[tab.buffer].content;
+        match result {
+            SearchResult::Pending => return,
+            SearchResult::Changed => search.update(content, search.origin),
+            SearchResult::Next => search.step(true),
+            SearchResult::Previous => search.step(false),
+            SearchResult::Close => {
+                self.last_search = self.search.take();
+                return;
+            }
+        }
+        match search.current_match() {
+            Some(found) => tab.editor.select_chars(found.start, found.end, content),
+            None => {
+                tab.editor
+                    .select_chars(search.origin, search.origin, content);
+                tab.editor.anchor = None;
+            }
+        }
+    }
+
+    /// Save the currently open file, asking for a path if it has none.
     pub fn save_current_file(&mut self) -> Result<()> {
-        if let Some(tab) = self.tabs.get_mut(self.current_tab) {
-            if let Some(path) = &tab.path {
-                let content = tab.content.to_string();
-                std::fs::write(path, content)?;
-                tab.modified = false;
-                let message = format!("Saved {}", path.display());
+        let Some(buffer) = self.get_current_buffer() else {
+            return Ok(());
+        };
+        match buffer.path.clone() {
+            Some(path) => self.save_current_to(path),
+            None => {
+                self.start_save_as();
+                Ok(())
+            }
+        }
+    }
+
+    /// Ask for a path to save the current tab to.
+    pub fn start_save_as(&mut self) {
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+        let base = self.file_explorer.current_path.clone();
+        let initial = buffer.path.as_ref().map_or(String::new(), |path| {
+            path.strip_prefix(&base)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned()
+        });
+        self.prompt =
+            Some(Prompt::new("Save as", initial, PromptAction::SaveAs).with_path_completion(base));
+    }
+
+    /// Save the current tab to `path`, asking before creating directories or replacing a file.
+    fn save_as(&mut self, path: PathBuf) -> Result<()> {
+        if path.is_dir() {
+            anyhow::bail!("{} is a directory", path.display());
+        }
+        let current = self.get_current_buffer().and_then(|b| b.path.as_ref());
+        if path
+            .parent()
+            .is_some_and(|p| !p.as_os_str().is_empty() && !p.exists())
+        {
+            let parent = path.parent().unwrap_or(&path).display().to_string();
+            self.confirm = Some(Confirm::yes_no(
+                format!("Directory {parent} does not exist. Create it?"),
+                ConfirmAction::CreateDirsOnSave { path },
+            ));
+        } else if path.exists() && current != Some(&path) {
+            self.confirm = Some(Confirm::yes_no(
+                format!("{} already exists. Overwrite?", path.display()),
+                ConfirmAction::OverwriteOnSave { path },
+            ));
+        } else {
+            self.save_current_to(path)?;
+        }
+        Ok(())
+    }
+
+    /// Write the current tab's buffer to `path`, making it the buffer's path.
+    fn save_current_to(&mut self, path: PathBuf) -> Result<()> {
+        let index = self.current_tab;
+        let Some(id) = self.tabs.get(index).map(|tab| tab.buffer) else {
+            return Ok(());
+        };
+        if self.buffers[id].path.as_ref() == Some(&path) && self.read_if_changed(id).is_some() {
+            // Never overwrite changes made by another program without asking
+            self.ask_about_external_change(path);
+            return Ok(());
+        }
+
+        let buffer = &mut self.buffers[id];
+        let content = buffer.content.to_string();
+        let bytes = buffer
+            .encoding
+            .encode(&buffer.line_ending.apply(&content))?;
+        let warnings = file_ops::write_atomic(&path, &bytes, &self.backup)?;
+        buffer.disk = Some(DiskState::new(&path, &bytes));
+        buffer.base_text = content;
+        buffer.history.mark_saved();
+        buffer.saved_format = (buffer.encoding, buffer.line_ending);
+        buffer.mixed_line_endings = false;
+        buffer.modified = false;
+        buffer.is_new = false;
+        buffer.orphaned = false;
+        let renamed = buffer.path.as_ref() != Some(&path);
+        if renamed {
+            self.buffers.set_path(id, path.clone(), &self.highlighter);
+        }
+        if renamed && path.starts_with(&self.file_explorer.root.path) {
+            self.refresh_explorer(None, &path);
+        }
+        if warnings.is_empty() {
+            self.set_status_message(format!("Saved {}", path.display()));
+        } else {
+            let warnings = warnings.join("; ");
+            self.set_status_message(format!("Saved {}, but {warnings}", path.display()));
+        }
+        match self.after_save.take() {
+            Some(AfterSave::Quit) => self.save_all_and_quit(),
+            Some(AfterSave::Close(id)) => {
+                if let Some(index) = self.tabs.iter().position(|tab| tab.buffer == id) {
+                    self.remove_tab(index);
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Switch the current tab to the next encoding; it is used from the next save on.
+    pub fn cycle_encoding(&mut self) {
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.encoding = buffer.encoding.next();
+            buffer.update_modified();
+            let message = format!("Encoding: {}", buffer.encoding.name());
+            self.set_status_message(message);
+        }
+    }
+
+    /// Switch the current tab to the next line ending; it is used from the next save on.
+    pub fn cycle_line_ending(&mut self) {
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.line_ending = buffer.line_ending.next();
+            buffer.update_modified();
+            let message = format!("Line endings: {}", buffer.line_ending.name());
+            self.set_status_message(message);
+        }
+    }
+
+    /// Check whether open files were changed by other programs, reloading unmodified tabs
+    /// and asking what to do with modified ones.
+    pub fn check_external_changes(&mut self) {
+        if let Some(watcher) = &mut self.watcher {
+            watcher.watch_files(self.buffers.iter().filter_map(|(_, b)| b.path.as_deref()));
+            // Files in directories that could not be watched are checked on every tick
+            if !watcher.has_changes() && !self.disk_check_pending && watcher.watches_all() {
+                return;
+            }
+        }
+        self.check_tabs_on_disk();
+    }
+
+    fn check_tabs_on_disk(&mut self) {
+        self.disk_check_pending = false;
+        let ids: Vec<BufferId> = self.buffers.iter().map(|(id, _)| id).collect();
+        for id in ids {
+            let buffer = &mut self.buffers[id];
+            let Some(path) = buffer.path.clone().filter(|_| buffer.disk.is_some()) else {
+                continue;
+            };
+            if !path.exists() {
+                if !buffer.orphaned {
+                    buffer.orphaned = true;
+                    let message = format!("{} was deleted on disk", buffer.name);
+                    self.set_status_message(message);
+                }
+                continue;
+            }
+            buffer.orphaned = false;
+
+            let Some(bytes) = self.read_if_changed(id) else {
+                continue;
+            };
+            let buffer = &mut self.buffers[id];
+            if !buffer.modified {
+                buffer.reload(&bytes);
+                buffer.disk = Some(DiskState::new(&path, &bytes));
+                let message = format!(
+                    "Reloaded {} (changed on disk){}",
+                    buffer.name,
+                    buffer.line_ending_warning()
+                );
+                self.clamp_views();
                 self.set_status_message(message);
+            } else if self.confirm.is_none() && self.prompt.is_none() && self.finder.is_none() {
+                self.ask_about_external_change(path);
             } else {
-                // TODO: Implement save as dialog
-                self.set_status_message("Save as not implemented yet".to_string());
+                self.disk_check_pending = true;
             }
         }
-        Ok(())
     }
 
-    /// Toggle the file explorer panel.
-    pub fn toggle_panel(&mut self) {
-        self.show_file_explorer = !self.show_file_explorer;
+    /// The new contents of the buffer's file if another program changed it since the buffer
+    /// was last loaded or saved.
+    fn read_if_changed(&mut self, id: BufferId) -> Option<Vec<u8>> {
+        let buffer = self.buffers.get_mut(id)?;
+        let path = buffer.path.as_ref()?;
+        let known = buffer.disk.as_ref()?;
+        if known.matches(&std::fs::metadata(path).ok()?) {
+            return None;
+        }
+        let bytes = std::fs::read(path).ok()?;
+        let current = DiskState::new(path, &bytes);
+        if current.hash == known.hash {
+            // Touched but not changed
+            buffer.disk = Some(current);
+            return None;
+        }
+        Some(bytes)
     }
 
-    /// Handle a key event for the current tab/editor.
-    pub fn handle_input(&mut self, key: KeyEvent) {
-        if let Some(tab) = self.tabs.get_mut(self.current_tab) {
-            tab.editor.handle_input(key, &mut tab.content);
-            tab.modified = true;
+    fn ask_about_external_change(&mut self, path: PathBuf) {
+        self.confirm = Some(Confirm::new(
+            format!("{} was changed on disk.", file_name(&path)),
+            &[('r', "Reload"), ('k', "Keep mine"), ('m', "Merge")],
+            ConfirmAction::ExternalChange { path },
+        ));
+    }
+
+    /// Resolve a change on disk to the modified buffer of `path`: 'r' reloads the file,
+    /// 'k' keeps the buffer and 'm' merges both versions, marking conflicts in the buffer.
+    /// Quitting or closing the tab after saving carries on unless there are conflicts to
+    /// look at first.
+    fn resolve_external_change(&mut self, path: &Path, choice: char) {
+        let after_save = self.after_save.take();
+        let mut resume = true;
+        let Some(id) = self.buffers.find(path) else {
+            return;
+        };
+        let Ok(bytes) = std::fs::read(path) else {
+            return;
+        };
+        let buffer = &mut self.buffers[id];
+        let message = match choice {
+            'r' => {
+                buffer.reload(&bytes);
+                format!("Reloaded {}{}", buffer.name, buffer.line_ending_warning())
+            }
+            'k' => {
+                buffer.base_text = LineEnding::normalize(&Encoding::decode(&bytes).0);
+                buffer.history.mark_unsaved();
+                buffer.update_modified();
+                format!(
+                    "Kept your version of {}; saving will overwrite the file",
+                    buffer.name
+                )
+            }
+            'm' => {
+                let theirs = LineEnding::normalize(&Encoding::decode(&bytes).0);
+                let merged = merge::merge3(&buffer.base_text, &buffer.content.to_string(), &theirs);
+                buffer.replace_all(&merged.text);
+                buffer.history.mark_unsaved();
+                buffer.base_text = theirs;
+                buffer.update_modified();
+                resume = merged.conflicts == 0;
+                match merged.conflicts {
+                    0 => format!("Merged changes on disk into {}", buffer.name),
+                    n => format!("Merged with {n} conflict(s) marked with <<<<<<< and >>>>>>>"),
+                }
+            }
+            _ => return,
+        };
+        buffer.disk = Some(DiskState::new(path, &bytes));
+        self.clamp_views();
+        self.set_status_message(message);
+        match after_save {
+            Some(AfterSave::Quit) if resume => self.save_all_and_quit(),
+            Some(AfterSave::Close(id)) if resume => {
+                if let Some(index) = self.tabs.iter().position(|tab| tab.buffer == id) {
+                    self.save_and_close(index);
+                }
+            }
+            _ => {}
         }
     }
 
-    /// Set a status message to be shown in the status bar.
-    pub fn set_status_message(&mut self, message: String) {
-        self.status_message = Some(message);
-        self.status_timer = 0;
+    /// Save the buffer of tab `index` if it is modified, then close the tab.
+    fn save_and_close(&mut self, index: usize) {
+        let Some(id) = self.tabs.get(index).map(|tab| tab.buffer) else {
+            return;
+        };
+        if !self.buffers[id].modified {
+            self.remove_tab(index);
+            return;
+        }
+        self.current_tab = index;
+        self.after_save = Some(AfterSave::Close(id));
+        if let Err(e) = self.save_current_file() {
+            self.after_save = None;
+            self.set_status_message(format!("Error saving file: {e}"));
+        }
     }
 
-    /// Get the currently selected tab, if any.
-    pub fn get_current_tab(&self) -> Option<&Tab> {
-        self.tabs.get(self.current_tab)
+    /// Periodic work between input events: noticing changes on disk, writing swap files and
+    /// offering to recover swap files of an earlier session.
+    pub fn tick(&mut self) {
+        self.check_external_changes();
+        if self.last_swap_write.elapsed() >= SWAP_INTERVAL {
+            self.write_swap_files();
+        }
+        self.ask_next_recovery();
     }
 
-    /// Set the root directory for the file explorer.
-    pub fn set_directory<P: Into<PathBuf>>(&mut self, dir: P) -> Result<()> {
-        let dir = dir.into();
-        self.file_explorer.current_path = dir.clone();
-        self.file_explorer.root = crate::file_explorer::FileNode::new(dir);
-        self.file_explorer.root.load_children()?;
-        self.file_explorer.root.expanded = true;
-        Ok(())
+    /// Write the contents of modified buffers to swap files, and remove the swap files of
+    /// buffers that no longer have unsaved changes.
+    pub fn write_swap_files(&mut self) {
+        self.last_swap_write = Instant::now();
+        let Some(store) = &self.swap else {
+            return;
+        };
+        for (_, buffer) in self.buffers.iter_mut() {
+            let wanted = buffer
+                .modified
+                .then(|| store.name_for(buffer.path.as_deref(), buffer.untitled_id));
+            if let Some((name, _)) = buffer
+                .swap
+                .take_if(|(name, _)| wanted.as_ref() != Some(name))
+            {
+                store.remove(&name);
+            }
+            let Some(name) = wanted else {
+                continue;
+            };
+            let content = buffer.content.to_string();
+            let hash = hash_bytes(content.as_bytes());
+            if buffer.swap.as_ref().is_some_and(|(_, h)| *h == hash) {
+                continue;
+            }
+            if store.write(&name, buffer.path.as_deref(), &content).is_ok() {
+                buffer.swap = Some((name, hash));
+            }
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Remove the swap files of all buffers, e.g. when quitting normally.
+    pub fn remove_swap_files(&mut self) {
+        let Some(store) = &self.swap else {
+            return;
+        };
+        for (_, buffer) in self.buffers.iter_mut() {
+            if let Some((name, _)) = buffer.swap.take() {
+                store.remove(&name);
+            }
+        }
+    }
 
-    #[test]
-    fn test_app_new_creates_initial_tab() {
-        let app = App::new().unwrap();
-        assert_eq!(app.tabs.len(), 1);
-        assert_eq!(app.current_tab, 0);
-        assert_eq!(app.tabs[0].name, "Untitled");
+    /// Offer to recover every swap file left behind by an earlier session.
+    pub fn find_leftover_swap_files(&mut self) {
+        let Some(store) = &self.swap else {
+            return;
+        };
+        for swap in store.leftovers() {
+            if !self
+                .recoveries
+                .iter()
+                .any(|r| r.swap_path == swap.swap_path)
+            {
+                self.recoveries.push(swap);
+            }
+        }
+        self.ask_next_recovery();
+    }
+
+    /// Offer to recover the swap file of an earlier session for `path`, if there is one.
+    fn queue_leftover_swap(&mut self, path: &Path) {
+        let Some(swap) = self
+            .swap
+            .as_ref()
+            .and_then(|store| store.leftover_for(path))
+        else {
+            return;
+        };
+        if !self
+            .recoveries
+            .iter()
+            .any(|r| r.swap_path == swap.swap_path)
+        {
+            // Asked about before the leftovers found at startup
+            self.recoveries.push(swap);
+        }
+        self.ask_next_recovery();
+    }
+
+    fn ask_next_recovery(&mut self) {
+        if self.confirm.is_some() || self.prompt.is_some() || self.finder.is_some() {
+            return;
+        }
+        let Some(swap) = self.recoveries.pop() else {
+            return;
+        };
+        let action = ConfirmAction::RecoverSwap {
+            swap: swap.swap_path,
+        };
+        self.confirm = Some(match &swap.path {
+            Some(path) => Confirm::new(
+                format!(
+                    "Unsaved changes to {} were found from an earlier session.",
+                    file_name(path)
+                ),
+                &[('r', "Recover"), ('d', "Diff"), ('x', "Discard")],
+                action,
+            ),
+            None => Confirm::new(
+                "An unsaved untitled buffer was found from an earlier session.",
+                &[('r', "Recover"), ('x', "Discard")],
+                action,
+            ),
+        });
+    }
+
+    /// Handle the answer to a recovery question: 'r' restores the swap file's contents into
+    /// a tab, 'd' shows how they differ from the file on disk and 'x' deletes the swap file.
+    fn resolve_recovery(&mut self, swap_path: &Path, choice: char) {
+        let Some(swap) = swap::read(swap_path) else {
+            return;
+        };
+        match choice {
+            'r' => {
+                let id = match &swap.path {
+                    Some(path) => {
+                        if let Err(e) = self.open_file(path.clone()) {
+                            self.set_status_message(format!("Error opening file: {e}"));
+                            return;
+                        }
+                        match self.buffers.find(path) {
+                            Some(id) => id,
+                            None => {
+                                // Not opened yet, e.g. waiting to confirm opening a binary
+                                // file; ask again afterwards
+                                self.recoveries.push(swap);
+                                return;
+                            }
+                        }
+                    }
+                    None => {
+                        self.new_file();
+                        self.tabs[self.current_tab].buffer
+                    }
+                };
+                let buffer = &mut self.buffers[id];
+                buffer.replace_all(&swap.content);
+                buffer.update_modified();
+                let message = format!("Recovered unsaved changes to {}", buffer.name);
+                self.clamp_views();
+                let _ = std::fs::remove_file(swap_path);
+                self.set_status_message(message);
+            }
+            'd' => {
+                let Some(path) = swap.path.clone() else {
+                    return;
+                };
+                let disk = std::fs::read(&path).unwrap_or_default();
+                let disk = LineEnding::normalize(&Encoding::decode(&disk).0);
+                let diff = similar::TextDiff::from_lines(&disk, &swap.content)
+                    .unified_diff()
+                    .header("on disk", "recovered")
+                    .to_string();
+                self.new_file();
+                let buffer = &mut self.buffers[self.tabs[self.current_tab].buffer];
+                buffer.content = Rope::from(diff);
+                buffer.name = format!("{} (recovery diff)", file_name(&path));
+                // Ask again, now with the differences on screen
+                self.recoveries.push(swap);
+            }
+            'x' => {
+                let _ = std::fs::remove_file(swap_path);
+                self.set_status_message("Discarded the recovered changes".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// Close the tab at `index`, asking first if it is the last view of a buffer with
+    /// unsaved changes.
+    pub fn close_tab(&mut self, index: usize) {
+        let Some(tab) = self.tabs.get(index) else {
+            return;
+        };
+        let buffer = &self.buffers[tab.buffer];
+        if !buffer.modified || self.views_of(tab.buffer, Some(index)) > 0 {
+            self.remove_tab(index);
+            return;
+        }
+        self.current_tab = index;
+        self.confirm = Some(Confirm::new(
+            format!("{} has unsaved changes.", buffer.name),
+            &[('s', "Save"), ('d', "Discard"), ('c', "Cancel")],
+            ConfirmAction::CloseTab { index },
+        ));
+    }
+
+    /// Close every tab except the current one. Tabs with unsaved changes are kept open.
+    pub fn close_other_tabs(&mut self) {
+        let mut kept = 0;
+        for index in (0..self.tabs.len()).rev() {
+            if index == self.current_tab {
+                continue;
+            }
+            let id = self.tabs[index].buffer;
+            if self.buffers[id].modified && self.views_of(id, Some(index)) == 0 {
+                kept += 1;
+            } else {
+                self.remove_tab(index);
+            }
+        }
+        if kept > 0 {
+            self.set_status_message(format!("Kept {kept} tab(s) with unsaved changes"));
+        }
+    }
+
+    /// Close the tab at `index` without asking, remembering its file for
+    /// [`Self::reopen_closed_tab`]. The buffer is dropped along with its last view, and
+    /// closing the last tab leaves an empty untitled one.
+    fn remove_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        let tab = self.tabs.remove(index);
+        let name = self.buffers[tab.buffer].name.clone();
+        if let Some(path) = self.buffers[tab.buffer].path.clone() {
+            self.closed_tabs.retain(|closed| closed.path != path);
+            if self.closed_tabs.len() == CLOSED_TABS_LIMIT {
+                self.closed_tabs.remove(0);
+            }
+            self.closed_tabs.push(ClosedTab {
+                path,
+                cursor: tab.editor.cursor,
+                scroll_offset: tab.editor.scroll_offset,
+            });
+        }
+        if index < self.current_tab || self.current_tab == self.tabs.len() {
+            self.current_tab = self.current_tab.saturating_sub(1);
+        }
+        if self.tabs.is_empty() {
+            self.new_file();
+        }
+        self.tab_removed(index);
+        if self.views_of(tab.buffer, None) == 0 {
+            let buffer = self.buffers.remove(tab.buffer);
+            if let (Some(store), Some((name, _))) =
+                (&self.swap, buffer.and_then(|mut b| b.swap.take()))
+            {
+                store.remove(&name);
+            }
+        }
+        self.set_status_message(format!("Closed {name}"));
+    }
+
+    /// Number of tabs and unfocused panes showing buffer `id`, leaving out tab `closing`
+    /// and the panes on it, which move to another tab when it closes.
+    fn views_of(&self, id: BufferId, closing: Option<usize>) -> usize {
+        let shows = |index: usize| {
+            Some(index) != closing && self.tabs.get(index).is_some_and(|tab| tab.buffer == id)
+        };
+        let tabs = (0..self.tabs.len()).filter(|&index| shows(index)).count();
+        tabs + self.panes.others().filter(|pane| shows(pane.tab)).count()
+    }
+
+    /// Reopen the most recently closed tab at its last cursor position.
+    pub fn reopen_closed_tab(&mut self) {
+        let Some(closed) = self.closed_tabs.pop() else {
+            self.set_status_message("No closed tabs to reopen".to_string());
+            return;
+        };
+        if let Err(e) = self.open_file(closed.path) {
+            self.set_status_message(format!("Error opening file: {e}"));
+            return;
+        }
+        if let Some(tab) = self.tabs.get_mut(self.current_tab) {
+            let content = &self.buffers[tab.buffer].content;
+            tab.editor.move_to(closed.cursor, content, false);
+            tab.editor.scroll_offset = closed.scroll_offset;
+        }
+    }
+
+    /// Switch to the tab `delta` positions away, wrapping around at either end.
+    pub fn cycle_tab(&mut self, delta: isize) {
+        let len = self.tabs.len() as isize;
+        if len > 0 {
+            self.current_tab = (self.current_tab as isize + delta).rem_euclid(len) as usize;
+        }
+    }
+
+    /// Switch to the tab at `index`, if there is one.
+    pub fn select_tab(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.current_tab = index;
+        }
+    }
+
+    /// Move the current tab `delta` positions to the left or right.
+    pub fn move_tab(&mut self, delta: isize) {
+        let Some(target) = self
+            .current_tab
+            .checked_add_signed(delta)
+            .filter(|&target| target < self.tabs.len())
+        else {
+            return;
+        };
+        let tab = self.tabs.remove(self.current_tab);
+        self.tabs.insert(target, tab);
+        for pane in self.panes.others_mut() {
+            if pane.tab == self.current_tab {
+                pane.tab = target;
+            } else if pane.tab == target {
+                pane.tab = self.current_tab;
+            }
+        }
+        self.current_tab = target;
+    }
+
+    /// Point panes at the right tabs after the tab at `index` was removed. Panes that
+    /// showed it switch to the current tab.
+    fn tab_removed(&mut self, index: usize) {
+        let Some(current) = self.tabs.get(self.current_tab) else {
+            return;
+        };
+        for pane in self.panes.others_mut() {
+            if pane.tab == index {
+                pane.tab = self.current_tab;
+                pane.editor = current.editor.clone();
+            } else if pane.tab > index {
+                pane.tab -= 1;
+            }
+        }
+    }
+
+    /// The current tab and its view, as kept for the focused pane when it loses focus.
+    fn current_pane(&self) -> Pane {
+        Pane {
+            tab: self.current_tab,
+            editor: self
+                .tabs
+                .get(self.current_tab)
+                .map_or_else(Editor::new, |tab| tab.editor.clone()),
+        }
+    }
+
+    /// Show `pane` in the focused pane: switch to its tab with its view.
+    fn show_pane(&mut self, pane: Pane) {
+        if let Some(tab) = self.tabs.get_mut(pane.tab) {
+            tab.editor = pane.editor;
+            tab.editor.clamp_to(&self.buffers[tab.buffer].content);
+            self.current_tab = pane.tab;
+        }
+    }
+
+    /// Split the focused pane side by side (`Direction::Horizontal`) or stacked
+    /// (`Direction::Vertical`), showing the current tab in both halves.
+    pub fn split_pane(&mut self, direction: Direction) {
+        let current = self.current_pane();
+        self.panes.split(direction, current);
+        self.focus = Focus::Editor;
+    }
+
+    /// Focus pane `id`.
+    pub fn focus_pane(&mut self, id: usize) {
+        let current = self.current_pane();
+        if let Some(pane) = self.panes.focus(id, current) {
+            self.show_pane(pane);
+        }
+        self.focus = Focus::Editor;
+    }
+
+    /// Focus the pane next to the focused one, to the right or below when `forward`.
+    pub fn focus_pane_towards(&mut self, direction: Direction, forward: bool) {
+        let areas: Vec<(usize, Rect)> = self.areas.panes.iter().map(|p| (p.id, p.area)).collect();
+        if let Some(id) = pane::neighbor(&areas, self.panes.focused(), direction, forward) {
+            self.focus_pane(id);
+        }
+    }
+
+    /// Close the focused pane; its tabs stay open.
+    pub fn close_pane(&mut self) {
+        match self.panes.close_focused() {
+            Some(pane) => self.show_pane(pane),
+            None => self.set_status_message("Cannot close the only pane".to_string()),
+        }
+    }
+
+    /// Move the divider next to the focused pane by `delta` percent.
+    pub fn resize_pane(&mut self, direction: Direction, delta: i16) {
+        self.panes.resize(direction, delta);
+    }
+
+    /// Quit, asking what to do first if any buffer has unsaved changes.
+    pub fn request_quit(&mut self) {
+        let unsaved = self.buffers.iter().filter(|(_, b)| b.modified).count();
+        if unsaved == 0 {
+            self.should_quit = true;
+            return;
+        }
+        self.confirm = Some(Confirm::new(
+            format!("{unsaved} file(s) have unsaved changes."),
+            &[('s', "Save all"), ('d', "Discard"), ('c', "Cancel")],
+            ConfirmAction::Quit,
+        ));
+    }
+
+    /// Save every modified tab and quit. Untitled tabs are saved one by one through the
+    /// Save As prompt, quitting once the last of them is saved.
+    fn save_all_and_quit(&mut self) {
+        self.after_save = None;
+        for index in 0..self.tabs.len() {
+            let buffer = &self.buffers[self.tabs[index].buffer];
+            let Some(path) = buffer.path.clone().filter(|_| buffer.modified) else {
+                continue;
+            };
+            let previous = std::mem::replace(&mut self.current_tab, index);
+            let result = self.save_current_to(path);
+            self.current_tab = previous;
+            if let Err(e) = result {
+                self.current_tab = index;
+                self.set_status_message(format!("Error saving file: {e}"));
+                return;
+            }
+            if self.confirm.is_some() {
+                // The file changed on disk; carry on once that is resolved
+                self.current_tab = index;
+                self.after_save = Some(AfterSave::Quit);
+                return;
+            }
+        }
+
+        match self.tabs.iter().position(|t| {
+            let buffer = &self.buffers[t.buffer];
+            buffer.modified && buffer.path.is_none()
+        }) {
+            Some(index) => {
+                self.current_tab = index;
+                self.after_save = Some(AfterSave::Quit);
+                self.start_save_as();
+            }
+            None => self.should_quit = true,
+        }
+    }
+
+    /// Toggle the file explorer panel.
+    pub fn toggle_panel(&mut self) {
+        self.show_file_explorer = !self.show_file_explorer;
+        if !self.show_file_explorer {
+            self.focus = Focus::Editor;
+        }
+    }
+
+    /// Move key focus between the file explorer and the editor, showing the explorer if needed.
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Editor => {
+                self.show_file_explorer = true;
+                Focus::Explorer
+            }
+            Focus::Explorer => Focus::Editor,
+        };
+    }
+
+    /// Handle a key event for the focused panel.
+    pub fn handle_input(&mut self, key: KeyEvent) {
+        if self.focus == Focus::Explorer {
+            self.handle_explorer_input(key);
+            return;
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match key.code {
+                KeyCode::Char('c') => return self.copy_selection(),
+                KeyCode::Char('x') => return self.cut_selection(),
+                KeyCode::Char('v') => return self.paste(),
+                _ => {}
+            }
+        }
+
+        if let Some(tab) = self.tabs.get_mut(self.current_tab) {
+            let buffer = &mut self.buffers[tab.buffer];
+            tab.editor
+                .handle_input(key, &mut buffer.content, &mut buffer.history);
+            buffer.update_modified();
+        }
+    }
+
+    fn handle_explorer_input(&mut self, key: KeyEvent) {
+        let page = self
+            .areas
+            .explorer
+            .map_or(1, |a| a.height.saturating_sub(2).max(1)) as isize;
+        let explorer = &mut self.file_explorer;
+        // Letter commands must not fire for shortcuts such as Ctrl+C
+        let plain = |c: char| {
+            key.modifiers.is_empty() || (c == 'N' && key.modifiers == KeyModifiers::SHIFT)
+        };
+        match key.code {
+            KeyCode::Char(c) if !plain(c) => {}
+            KeyCode::Up => explorer.move_selection(-1),
+            KeyCode::Down => explorer.move_selection(1),
+            KeyCode::PageUp => explorer.move_selection(-page),
+            KeyCode::PageDown => explorer.move_selection(page),
+            KeyCode::Home => explorer.selected_index = 0,
+            KeyCode::End => explorer.select_last(),
+            KeyCode::Left => explorer.collapse_selected(),
+            KeyCode::Right => {
+                if let Err(e) = explorer.expand_selected() {
+                    self.set_status_message(format!("Error reading directory: {e}"));
+                }
+            }
+            KeyCode::Enter => {
+                let index = explorer.selected_index;
+                self.activate_explorer_entry(index);
+            }
+            KeyCode::Esc => self.focus = Focus::Editor,
+            KeyCode::Char('n') => self.start_file_operation(FileOperation::NewFile),
+            KeyCode::Char('N') => self.start_file_operation(FileOperation::NewDirectory),
+            KeyCode::Char('r') | KeyCode::F(2) => self.start_file_operation(FileOperation::Rename),
+            KeyCode::Char('c') => self.start_file_operation(FileOperation::Duplicate),
+            KeyCode::Char('m') => self.start_file_operation(FileOperation::Move),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                self.start_file_operation(FileOperation::Delete)
+            }
+            KeyCode::Char('i') => {
+                if let Err(e) = self.file_explorer.toggle_show_ignored() {
+                    self.set_status_message(format!("Error refreshing explorer: {e}"));
+                } else if self.file_explorer.show_ignored {
+                    self.set_status_message("Showing ignored files".to_string());
+                } else {
+                    self.set_status_message("Hiding ignored files".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the prompt or confirmation for a file operation on the selected explorer entry.
+    fn start_file_operation(&mut self, operation: FileOperation) {
+        let Some(node) = self.file_explorer.selected_node_mut() else {
+            return;
+        };
+        let (path, is_dir) = (node.path.clone(), node.is_dir);
+        let is_root = path == self.file_explorer.root.path;
+        // New entries go into the selected directory, or next to the selected file
+        let dir = if is_dir {
+            path.clone()
+        } else {
+            path.parent()
+                .map_or_else(|| path.clone(), Path::to_path_buf)
+        };
+
+        if is_root
+            && !matches!(
+                operation,
+                FileOperation::NewFile | FileOperation::NewDirectory
+            )
+        {
+            self.set_status_message("Cannot modify the explorer root".to_string());
+            return;
+        }
+
+        let name = file_name(&path);
+        self.prompt = Some(match operation {
+            FileOperation::NewFile => Prompt::new("New file", "", PromptAction::NewFile { dir }),
+            FileOperation::NewDirectory => {
+                Prompt::new("New directory", "", PromptAction::NewDirectory { dir })
+            }
+            FileOperation::Rename => Prompt::new("Rename to", name, PromptAction::Rename { path }),
+            FileOperation::Duplicate => Prompt::new(
+                "Duplicate as",
+                file_ops::duplicate_name(&path),
+                PromptAction::Duplicate { path },
+            ),
+            FileOperation::Move => {
+                let relative = path
+                    .strip_prefix(&self.file_explorer.current_path)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .into_owned();
+                Prompt::new("Move to", relative, PromptAction::Move { path })
+            }
+            FileOperation::Delete => {
+                self.confirm = Some(Confirm::yes_no(
+                    format!("Delete {name}?"),
+                    ConfirmAction::Delete { path },
+                ));
+                return;
+            }
+        });
+    }
+
+    /// Handle a key while a prompt or confirmation is open.
+    pub fn handle_modal_input(&mut self, key: KeyEvent) {
+        if let Some(confirm) = &self.confirm {
+            if key.code == KeyCode::Esc {
+                self.confirm = None;
+                self.after_save = None;
+            } else if let Some(choice) = confirm.choice_for(key) {
+                let action = confirm.action.clone();
+                self.confirm = None;
+                self.run_confirm_action(action, choice);
+            }
+            return;
+        }
+
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        match prompt.handle_key(key) {
+            PromptResult::Pending => {}
+            PromptResult::Cancel => {
+                self.prompt = None;
+                self.after_save = None;
+            }
+            PromptResult::Submit(input) => {
+                let action = prompt.action.clone();
+                self.prompt = None;
+                let input = input.trim();
+                if !input.is_empty() {
+                    if let Err(e) = self.run_prompt_action(action, input) {
+                        self.set_status_message(format!("Error: {e}"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn run_prompt_action(&mut self, action: PromptAction, input: &str) -> Result<()> {
+        match action {
+            PromptAction::NewFile { dir } => {
+                let path = dir.join(input);
+                file_ops::create_file(&path)?;
+                self.refresh_explorer(Some(&dir), &path);
+                self.open_file(path)?;
+            }
+            PromptAction::NewDirectory { dir } => {
+                let path = dir.join(input);
+                file_ops::create_dir(&path)?;
+                self.refresh_explorer(Some(&dir), &path);
+                self.set_status_message(format!("Created {}", path.display()));
+            }
+            PromptAction::Rename { path } => {
+                let target = path.with_file_name(input);
+                file_ops::move_path(&path, &target)?;
+                self.retarget_buffers(&path, &target);
+                self.refresh_explorer(None, &target);
+                self.set_status_message(format!("Renamed to {}", target.display()));
+            }
+            PromptAction::Duplicate { path } => {
+                let target = path.with_file_name(input);
+                file_ops::copy_path(&path, &target)?;
+                self.refresh_explorer(None, &target);
+                self.set_status_message(format!("Created {}", target.display()));
+            }
+            PromptAction::Move { path } => {
+                let target =
+                    file_ops::move_destination(&path, input, &self.file_explorer.current_path);
+                file_ops::move_path(&path, &target)?;
+                self.retarget_buffers(&path, &target);
+                self.refresh_explorer(target.parent(), &target);
+                self.set_status_message(format!("Moved to {}", target.display()));
+            }
+            PromptAction::SaveAs => {
+                let path = self.file_explorer.current_path.join(input);
+                self.save_as(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_confirm_action(&mut self, action: ConfirmAction, choice: char) {
+        match action {
+            ConfirmAction::Delete { path } => {
+                if choice != 'y' {
+                    return;
+                }
+                match file_ops::delete_path(&path) {
+                    Ok(()) => {
+                        for (_, buffer) in self.buffers.iter_mut() {
+                            if buffer.path.as_ref().is_some_and(|p| p.starts_with(&path)) {
+                                buffer.orphaned = true;
+                            }
+                        }
+                        self.refresh_explorer(None, &path);
+                        self.set_status_message(format!("Deleted {}", path.display()));
+                    }
+                    Err(e) => self.set_status_message(format!("Error deleting: {e}")),
+                }
+            }
+            ConfirmAction::OverwriteOnSave { path } => {
+                if choice != 'y' {
+                    self.after_save = None;
+                } else {
+                    if let Err(e) = self.save_current_to(path) {
+                        self.set_status_message(format!("Error saving file: {e}"));
+                    }
+                }
+            }
+            ConfirmAction::CreateDirsOnSave { path } => {
+                if choice != 'y' {
+                    self.after_save = None;
+                    return;
+                }
+                let result = path
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|()| self.save_as(path));
+                if let Err(e) = result {
+                    self.set_status_message(format!("Error saving file: {e}"));
+                }
+            }
+            ConfirmAction::CloseTab { index } => match choice {
+                's' => self.save_and_close(index),
+                'd' => self.remove_tab(index),
+                _ => {}
+            },
+            ConfirmAction::Quit => match choice {
+                's' => self.save_all_and_quit(),
+                'd' => self.should_quit = true,
+                _ => {}
+            },
+            ConfirmAction::RecoverSwap { swap } => {
+                self.resolve_recovery(&swap, choice);
+            }
+            ConfirmAction::ExternalChange { path } => {
+                self.resolve_external_change(&path, choice);
+            }
+            ConfirmAction::OpenBinary { path } => {
+                if choice == 'y' {
+                    if let Err(e) = self.load_file(path, true) {
+                        self.set_status_message(format!("Error opening file: {e}"));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Update buffers whose file lives at or below `from` after it was moved to `to`.
+    fn retarget_buffers(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<(BufferId, PathBuf)> = self
+            .buffers
+            .iter()
+            .filter_map(|(id, buffer)| {
+                let rest = buffer.path.as_ref()?.strip_prefix(from).ok()?;
+                let path = if rest.as_os_str().is_empty() {
+                    to.to_path_buf()
+                } else {
+                    to.join(rest)
+                };
+                Some((id, path))
+            })
+            .collect();
+        for (id, path) in moved {
+            self.buffers.set_path(id, path, &self.highlighter);
+        }
+    }
+
+    /// Reload the explorer after a file operation and select `path`.
+    fn refresh_explorer(&mut self, expand: Option<&Path>, path: &Path) {
+        if let Err(e) = self.file_explorer.refresh(expand) {
+            self.set_status_message(format!("Error refreshing explorer: {e}"));
+        }
+        self.file_explorer.select_path(path);
+    }
+
+    /// Handle a mouse event using the areas of the last drawn frame.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let (x, y) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.mouse_selecting = false;
+                if let Some(pane) = self.pane_at(x, y) {
+                    if pane.id != self.panes.focused() {
+                        self.focus_pane(pane.id);
+                        self.areas.editor = pane.area;
+                        self.areas.editor_text = pane.text;
+                    }
+                }
+                if contains(self.areas.tabs, x, y) {
+                    if let Some(i) = self
+                        .areas
+                        .tab_titles
+                        .iter()
+                        .position(|&(start, end)| (start..end).contains(&x))
+                    {
+                        self.current_tab = i;
+                    }
+                } else if let Some(explorer) = self.areas.explorer.filter(|a| contains(*a, x, y)) {
+                    if y > explorer.y {
+                        let index =
+                            (y - explorer.y - 1) as usize + self.file_explorer.scroll_offset;
+                        self.activate_explorer_entry(index);
+                    }
+                } else if contains(self.areas.editor, x, y) {
+                    self.focus = Focus::Editor;
+                    let extend = mouse.modifiers.contains(KeyModifiers::SHIFT);
+                    self.move_cursor_to_screen(x, y, extend);
+                    if !extend {
+                        // Anchor the selection where the button went down so a drag extends it
+                        if let Some(tab) = self.tabs.get_mut(self.current_tab) {
+                            tab.editor.anchor = Some(tab.editor.cursor);
+                        }
+                    }
+                    self.mouse_selecting = true;
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.mouse_selecting => {
+                self.move_cursor_to_screen(x, y, true);
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.mouse_selecting = false;
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let delta = if mouse.kind == MouseEventKind::ScrollUp {
+                    -3
+                } else {
+                    3
+                };
+                if let Some(explorer) = self.areas.explorer.filter(|a| contains(*a, x, y)) {
+                    let height = explorer.height.saturating_sub(2) as usize;
+                    self.file_explorer.scroll_by(delta, height);
+                } else if let Some(area) = self.pane_at(x, y) {
+                    let height = area.text.height as usize;
+                    let view = match self.panes.get_mut(area.id) {
+                        Some(pane) => self
+                            .tabs
+                            .get(pane.tab)
+                            .map(|tab| (&mut pane.editor, tab.buffer)),
+                        None => self
+                            .tabs
+                            .get_mut(self.current_tab)
+                            .map(|tab| (&mut tab.editor, tab.buffer)),
+                    };
+                    if let Some((editor, id)) = view {
+                        editor.scroll_by(delta, &self.buffers[id].content, height);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The editor pane at a screen position.
+    fn pane_at(&self, x: u16, y: u16) -> Option<PaneArea> {
+        self.areas
+            .panes
+            .iter()
+            .find(|pane| contains(pane.area, x, y))
+            .copied()
+    }
+
+    /// Move the cursor to the text under a screen position, clamping positions outside
+    /// the text area to its edges.
+    fn move_cursor_to_screen(&mut self, x: u16, y: u16, extend: bool) {
+        let text = self.areas.editor_text;
+        let Some(tab) = self.tabs.get_mut(self.current_tab) else {
+            return;
+        };
+        let buffer = &mut self.buffers[tab.buffer];
+        let col = x.clamp(text.x, text.right().max(text.x)) - text.x;
+        let row = y.clamp(text.y, text.bottom().saturating_sub(1).max(text.y)) - text.y;
+        let row = tab.editor.scroll_offset.row + row as usize;
+        let column = tab.editor.scroll_offset.col + col as usize;
+        let position = Position::new(row, tab.editor.col_at_column(&buffer.content, row, column));
+        buffer.history.break_group();
+        tab.editor.move_to(position, &buffer.content, extend);
+    }
+
+    /// Select the explorer entry on display line `index` and open it:
+    /// directories are expanded or collapsed, files are opened in a tab and focused.
+    pub fn activate_explorer_entry(&mut self, index: usize) {
+        let show_ignored = self.file_explorer.show_ignored;
+        let Some(node) = self.file_explorer.node_at_mut(index) else {
+            return;
+        };
+        let file = if node.is_dir {
+            node.expanded = !node.expanded;
+            if node.expanded {
+                if let Err(e) = node.load_children(show_ignored) {
+                    node.expanded = false;
+                    let message = format!("Error reading {}: {e}", node.path.display());
+                    self.set_status_message(message);
+                }
+            }
+            None
+        } else {
+            Some(node.path.clone())
+        };
+        self.file_explorer.selected_index = index;
+        self.focus = Focus::Explorer;
+
+        if let Some(path) = file {
+            self.focus = Focus::Editor;
+            if let Err(e) = self.open_file(path) {
+                self.set_status_message(format!("Error opening file: {e}"));
+            }
+        }
+    }
+
+    /// Copy the current selection to the clipboard.
+    pub fn copy_selection(&mut self) {
+        let Some(tab) = self.tabs.get(self.current_tab) else {
+            return;
+        };
+        if let Some(text) = tab.editor.selected_text(&self.buffers[tab.buffer].content) {
+            self.copy_to_clipboard(&text);
+        }
+    }
+
+    /// Cut the current selection to the clipboard.
+    pub fn cut_selection(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.current_tab) else {
+            return;
+        };
+        let buffer = &mut self.buffers[tab.buffer];
+        if let Some(text) = tab
+            .editor
+            .cut_selection(&mut buffer.content, &mut buffer.history)
+        {
+            buffer.update_modified();
+            self.copy_to_clipboard(&text);
+        }
+    }
+
+    /// Paste the clipboard contents at the cursor, replacing any selection.
+    pub fn paste(&mut self) {
+        let text = self.clipboard.paste();
+        self.insert_text(&text);
+    }
+
+    /// Insert text pasted through the terminal (bracketed paste) as a single edit, or into
+    /// the input of an open prompt, finder or search bar.
+    pub fn paste_text(&mut self, text: &str) {
+        // Terminals commonly send line breaks as CR inside bracketed pastes
+        let text = LineEnding::normalize(text);
+        if self.show_help || self.confirm.is_some() {
+            return;
+        }
+        if let Some(prompt) = &mut self.prompt {
+            prompt.insert_str(&text);
+        } else if let Some(finder) = &mut self.finder {
+            finder.insert_str(&text);
+        } else if let Some(search) = &mut self.search {
+            let result = search.insert_str(&text);
+            self.apply_search_result(result);
+        } else {
+            self.insert_text(&text);
+        }
+    }
+
+    /// Whether a popup, overlay or the search bar is open and takes the input.
+    pub fn modal_open(&self) -> bool {
+        self.show_help
+            || self.prompt.is_some()
+            || self.confirm.is_some()
+            || self.finder.is_some()
+            || self.search.is_some()
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        // Clipboard tools may hand back CRLF, but the buffer only ever holds `\n`
+        let text = LineEnding::normalize(text);
+        if let Some(tab) = self.tabs.get_mut(self.current_tab) {
+            let buffer = &mut self.buffers[tab.buffer];
+            tab.editor
+                .insert_text(&text, &mut buffer.content, &mut buffer.history);
+            buffer.update_modified();
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str) {
+        let lines = text.lines().count().max(1);
+        match self.clipboard.copy(text) {
+            Ok(()) => self.set_status_message(format!("Copied {lines} line(s)")),
+            Err(e) => self.set_status_message(format!(
+                "Clipboard error: {e} (copied to internal register)"
+            )),
+        }
+    }
+
+    /// Set a status message to be shown in the status bar.
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_message = Some(message);
+        self.status_timer = 0;
+    }
+
+    /// Get the currently selected tab, if any.
+    pub fn get_current_tab(&self) -> Option<&Tab> {
+        self.tabs.get(self.current_tab)
+    }
+
+    /// Get the buffer shown in the current tab, if any.
+    pub fn get_current_buffer(&self) -> Option<&Buffer> {
+        self.tabs
+            .get(self.current_tab)
+            .and_then(|tab| self.buffers.get(tab.buffer))
+    }
+
+    fn get_current_buffer_mut(&mut self) -> Option<&mut Buffer> {
+        let id = self.tabs.get(self.current_tab)?.buffer;
+        self.buffers.get_mut(id)
+    }
+
+    /// Keep every view inside its buffer after the buffer was replaced as a whole, e.g.
+    /// by reloading it from disk.
+    fn clamp_views(&mut self) {
+        for tab in &mut self.tabs {
+            tab.editor.clamp_to(&self.buffers[tab.buffer].content);
+        }
+        for pane in self.panes.others_mut() {
+            if let Some(tab) = self.tabs.get(pane.tab) {
+                pane.editor.clamp_to(&self.buffers[tab.buffer].content);
+            }
+        }
+        if let Some(search) = &mut self.search {
+            if let Some(buffer) = self.buffers.get(search.buffer) {
+                let from = search.current_match().map_or(search.origin, |m| m.start);
+                search.update(&buffer.content, from);
+            }
+        }
+    }
+
+    /// Tab names, with just enough of their parent directories added to tell apart tabs
+    /// whose files have the same name, e.g. `editor/mod.rs` and `ui/mod.rs`.
+    pub fn tab_labels(&self) -> Vec<String> {
+        self.tabs
+            .iter()
+            .map(|tab| {
+                let buffer = &self.buffers[tab.buffer];
+                let Some(path) = &buffer.path else {
+                    return buffer.name.clone();
+                };
+                let others: Vec<&Path> = self
+                    .buffers
+                    .iter()
+                    .filter(|&(id, other)| id != tab.buffer && other.name == buffer.name)
+                    .filter_map(|(_, other)| other.path.as_deref())
+                    .collect();
+                if others.is_empty() {
+                    buffer.name.clone()
+                } else {
+                    distinguishing_label(path, &buffer.name, &others)
+                }
+            })
+            .collect()
+    }
+
+    /// Set the root directory for the file explorer.
+    pub fn set_directory<P: Into<PathBuf>>(&mut self, dir: P) -> Result<()> {
+        let dir = dir.into();
+        self.file_explorer.current_path = dir.clone();
+        self.file_explorer.root = crate::file_explorer::FileNode::new(dir);
+        self.file_explorer
+            .root
+            .load_children(self.file_explorer.show_ignored)?;
+        self.file_explorer.root.expanded = true;
+        Ok(())
+    }
+
+    /// The open files, cursors and panel state, to be restored on the next launch.
+    pub fn session(&self) -> Session {
+        let mut current_tab = 0;
+        let mut tabs = Vec::new();
+        for (index, tab) in self.tabs.iter().enumerate() {
+            let buffer = &self.buffers[tab.buffer];
+            let Some(path) = buffer.path.as_ref().filter(|_| !buffer.is_new) else {
+                continue;
+            };
+            if index == self.current_tab {
+                current_tab = tabs.len();
+            }
+            tabs.push(TabSession {
+                path: file_ops::absolute(path),
+                cursor: tab.editor.cursor,
+                scroll_offset: tab.editor.scroll_offset,
+            });
+        }
+        Session {
+            tabs,
+            current_tab,
+            explorer: ExplorerSession {
+                expanded: self.file_explorer.expanded_paths(),
+                selected_index: self.file_explorer.selected_index,
+                scroll_offset: self.file_explorer.scroll_offset,
+                show_ignored: self.file_explorer.show_ignored,
+            },
+            show_file_explorer: self.show_file_explorer,
+        }
+    }
+
+    /// Reopen the files of `session` and restore its cursors and panel state. Files that
+    /// no longer exist are skipped, and the initial empty tab is replaced.
+    pub fn restore_session(&mut self, session: Session) {
+        self.file_explorer.show_ignored = session.explorer.show_ignored;
+        if let Err(e) = self
+            .file_explorer
+            .restore_expanded(&session.explorer.expanded)
+        {
+            self.set_status_message(format!("Error restoring explorer: {e}"));
+        }
+        let last = self.file_explorer.len().saturating_sub(1);
+        self.file_explorer.selected_index = session.explorer.selected_index.min(last);
+        self.file_explorer.scroll_offset = session.explorer.scroll_offset.min(last);
+        self.show_file_explorer = session.show_file_explorer;
+
+        let untouched = self.get_current_buffer().is_some_and(|buffer| {
+            self.tabs.len() == 1
+                && buffer.path.is_none()
+                && !buffer.modified
+                && buffer.content.len_chars() == 0
+        });
+        let first = self.tabs.len();
+        let mut current = None;
+        for (index, saved) in session.tabs.into_iter().enumerate() {
+            if !saved.path.is_file() || self.load_file(saved.path, true).is_err() {
+                continue;
+            }
+            let tab = &mut self.tabs[self.current_tab];
+            let content = &self.buffers[tab.buffer].content;
+            tab.editor.move_to(saved.cursor, content, false);
+            let max_row = content.len_lines().saturating_sub(1);
+            tab.editor.scroll_offset = Position::new(
+                saved.scroll_offset.row.min(max_row),
+                saved.scroll_offset.col,
+            );
+            if index <= session.current_tab {
+                current = Some(self.current_tab);
+            }
+        }
+        let Some(current) = current.or((self.tabs.len() > first).then_some(first)) else {
+            return;
+        };
+        self.current_tab = current;
+        if untouched {
+            let tab = self.tabs.remove(0);
+            self.buffers.remove(tab.buffer);
+            self.current_tab -= 1;
+            self.tab_removed(0);
+        }
+        self.set_status_message(format!("Restored {} tab(s)", self.tabs.len()));
+    }
+
+    /// Restore the session saved for the explorer's directory, if there is one.
+    pub fn restore_saved_session(&mut self) {
+        let session = self
+            .sessions
+            .as_ref()
+            .and_then(|store| store.load(&self.file_explorer.current_path));
+        if let Some(session) = session {
+            self.restore_session(session);
+        }
+    }
+
+    /// Remember the open tabs for the explorer's directory.
+    pub fn save_session(&self) -> Result<()> {
+        match &self.sessions {
+            Some(store) => store.save(&self.file_explorer.current_path, &self.session()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// File operations available from the explorer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileOperation {
+    NewFile,
+    NewDirectory,
+    Rename,
+    Duplicate,
+    Move,
+    Delete,
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Untitled")
+        .to_string()
+}
+
+/// Names of the directories containing `path`, nearest first.
+fn parent_segments(path: &Path) -> Vec<String> {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.parent()
+        .into_iter()
+        .flat_map(Path::iter)
+        .map(|segment| segment.to_string_lossy().into_owned())
+        .rev()
+        .collect()
+}
+
+/// `name` prefixed with the nearest parent directory of `path` that the paths in `others`
+/// do not share, e.g. `src/mod.rs` or `a/…/mod.rs`.
+fn distinguishing_label(path: &Path, name: &str, others: &[&Path]) -> String {
+    let segments = parent_segments(path);
+    let others: Vec<Vec<String>> = others.iter().map(|p| parent_segments(p)).collect();
+    let sep = std::path::MAIN_SEPARATOR;
+    for depth in 1..=segments.len() {
+        let unique = others
+            .iter()
+            .all(|other| other.get(..depth) != segments.get(..depth));
+        if unique {
+            return match depth {
+                1 => format!("{}{sep}{name}", segments[0]),
+                _ => format!("{}{sep}…{sep}{name}", segments[depth - 1]),
+            };
+        }
+    }
+    path.display().to_string()
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    area.contains(ratatui::layout::Position::new(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(app: &App, index: usize) -> &Buffer {
+        &app.buffers[app.tabs[index].buffer]
+    }
+
+    #[test]
+    fn test_app_new_creates_initial_tab() {
+        let app = App::new().unwrap();
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.current_tab, 0);
+        assert_eq!(buffer(&app, 0).name, "Untitled");
+    }
+
+    #[test]
+    fn test_paste_text_is_single_undo_step() {
+        let mut app = App::new().unwrap();
+        app.paste_text("fn main() {\r    println!();\r}");
+        let buffer = buffer(&app, 0);
+        assert_eq!(
+            buffer.content.to_string(),
+            "fn main() {\n    println!();\n}"
+        );
+        assert!(buffer.modified);
+
+        app.handle_input(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        let buffer = app.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.to_string(), "");
+        assert!(!buffer.modified);
+
+        // Clipboard contents with CRLF line breaks are normalized too
+        app.clipboard.copy("a\r\nb").unwrap();
+        app.paste();
+        assert_eq!(
+            app.get_current_buffer().unwrap().content.to_string(),
+            "a\nb"
+        );
+    }
+
+    #[test]
+    fn test_paste_goes_to_open_input() {
+        let mut app = App::new().unwrap();
+        app.paste_text("one two\n");
+        app.start_save_as();
+        app.paste_text("a.txt\r\nignored");
+        assert_eq!(app.prompt.take().unwrap().input, "a.txt");
+
+        app.open_search();
+        app.paste_text("two");
+        assert_eq!(app.search.as_ref().unwrap().counter(), "1 of 1");
+        assert!(app.modal_open());
+        assert_eq!(buffer(&app, 0).content.to_string(), "one two\n");
+    }
+
+    #[test]
+    fn test_mouse_click_and_drag_in_editor() {
+        let mut app = App::new().unwrap();
+        app.paste_text("first line\nsecond line\n");
+        app.areas.editor = Rect::new(30, 3, 50, 20);
+        app.areas.editor_text = Rect::new(36, 4, 43, 18);
+        let mouse = |kind, column, row| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 38, 5));
+        assert_eq!(app.tabs[0].editor.cursor, Position::new(1, 2));
+        app.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 70, 4));
+        assert_eq!(app.tabs[0].editor.cursor, Position::new(0, 10));
+        let content = &buffer(&app, 0).content;
+        assert_eq!(
+            app.tabs[0].editor.selected_text(content).as_deref(),
+            Some("\nse")
+        );
+    }
+
+    #[test]
+    fn test_rename_and_delete_update_open_tabs() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_app_ops_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("old.rs");
+        std::fs::write(&file, "fn old() {}").unwrap();
+
+        let mut app = App::new().unwrap();
+        app.set_directory(&dir).unwrap();
+        app.open_file(&file).unwrap();
+        app.run_prompt_action(PromptAction::Rename { path: file.clone() }, "new.rs")
+            .unwrap();
+        let renamed = dir.join("new.rs");
+        assert_eq!(buffer(&app, 1).path.as_deref(), Some(renamed.as_path()));
+        assert_eq!(buffer(&app, 1).name, "new.rs");
+
+        app.run_confirm_action(
+            ConfirmAction::Delete {
+                path: renamed.clone(),
+            },
+            'y',
+        );
+        assert!(!renamed.exists());
+        assert!(buffer(&app, 1).orphaned);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_explorer_commands_ignore_modified_keys() {
+        let mut app = App::new().unwrap();
+        app.toggle_focus();
+        for modifiers in [KeyModifiers::CONTROL, KeyModifiers::ALT] {
+            app.handle_input(KeyEvent::new(KeyCode::Char('n'), modifiers));
+            assert!(app.prompt.is_none());
+        }
+        app.handle_input(KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT));
+        assert!(app.prompt.is_some());
+    }
+
+    #[test]
+    fn test_save_as_untitled_buffer() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_save_as_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut app = App::new().unwrap();
+        app.file_explorer.current_path = dir.clone();
+        app.paste_text("fn main() {}\n");
+
+        app.save_current_file().unwrap();
+        assert!(app.prompt.is_some());
+        app.prompt = None;
+        app.run_prompt_action(PromptAction::SaveAs, "src/main.rs")
+            .unwrap();
+        let confirm = app.confirm.take().unwrap();
+        assert!(matches!(
+            confirm.action,
+            ConfirmAction::CreateDirsOnSave { .. }
+        ));
+        app.run_confirm_action(confirm.action, 'y');
+
+        let path = dir.join("src").join("main.rs");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn main() {}\n");
+        let buffer = buffer(&app, 0);
+        assert_eq!(buffer.name, "main.rs");
+        assert_eq!(buffer.syntax, "Rust");
+        assert!(!buffer.modified);
+
+        app.new_file();
+        app.run_prompt_action(PromptAction::SaveAs, "src/main.rs")
+            .unwrap();
+        assert!(matches!(
+            app.confirm.as_ref().map(|c| &c.action),
+            Some(ConfirmAction::OverwriteOnSave { .. })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_file_reuses_existing_buffer() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_switch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "").unwrap();
+
+        let mut app = App::new().unwrap();
+        app.open_file(file.clone()).unwrap();
+        app.new_file();
+        app.open_file(dir.join(".").join("lib.rs")).unwrap();
+        assert_eq!(app.tabs.len(), 3);
+        assert_eq!(app.current_tab, 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_quit_with_unsaved_changes_asks_first() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_quit_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut app = App::new().unwrap();
+        app.file_explorer.current_path = dir.clone();
+        app.request_quit();
+        assert!(app.should_quit);
+
+        let mut app = App::new().unwrap();
+        app.file_explorer.current_path = dir.clone();
+        app.handle_input(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(buffer(&app, 0).content.to_string(), "q");
+        app.request_quit();
+        assert!(!app.should_quit);
+        let confirm = app.confirm.take().unwrap();
+
+        // Save all asks for a path for the untitled tab, then quits once it is saved
+        app.run_confirm_action(confirm.action, 's');
+        assert!(!app.should_quit);
+        assert!(app.prompt.take().is_some());
+        app.run_prompt_action(PromptAction::SaveAs, "q.txt")
+            .unwrap();
+        assert!(app.should_quit);
+        assert_eq!(std::fs::read_to_string(dir.join("q.txt")).unwrap(), "q");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_all_waits_for_changes_on_disk() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_save_all_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut app = App::new().unwrap();
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(dir.join(name), "text\n").unwrap();
+            app.open_file(dir.join(name)).unwrap();
+            app.handle_input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        }
+        std::fs::write(dir.join("a.txt"), "changed on disk\n").unwrap();
+
+        app.request_quit();
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 's');
+        let confirm = app.confirm.take().unwrap();
+        assert!(matches!(
+            confirm.action,
+            ConfirmAction::ExternalChange { .. }
+        ));
+        assert!(app.prompt.is_none());
+        assert!(!app.should_quit);
+
+        app.run_confirm_action(confirm.action, 'k');
+        assert!(app.should_quit);
+        for name in ["a.txt", "b.txt"] {
+            assert_eq!(std::fs::read_to_string(dir.join(name)).unwrap(), "!text\n");
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_close_tab_waits_for_changes_on_disk() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_close_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut app = App::new().unwrap();
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(dir.join(name), "text\n").unwrap();
+            app.open_file(dir.join(name)).unwrap();
+            app.handle_input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        }
+        std::fs::write(dir.join("a.txt"), "changed on disk\n").unwrap();
+
+        app.close_tab(1);
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 's');
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 'k');
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "!text\n"
+        );
+
+        // A later save must not close another tab
+        app.current_tab = 1;
+        app.save_current_file().unwrap();
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("b.txt")).unwrap(),
+            "!text\n"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_close_move_and_reopen_tabs() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_tabs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(dir.join(name), "text\n").unwrap();
+        }
+        let mut app = App::new().unwrap();
+        app.file_explorer.current_path = dir.clone();
+        app.open_file(dir.join("a.txt")).unwrap();
+        app.open_file(dir.join("b.txt")).unwrap();
+        let names = |app: &App| {
+            (0..app.tabs.len())
+                .map(|i| buffer(app, i).name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        app.cycle_tab(1);
+        assert_eq!(app.current_tab, 0);
+        app.move_tab(1);
+        assert_eq!(names(&app), ["a.txt", "Untitled", "b.txt"]);
+        assert_eq!(app.current_tab, 1);
+
+        // Closing a modified tab asks first; saving an untitled one goes through Save As
+        app.handle_input(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+        app.close_tab(1);
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 's');
+        assert!(app.prompt.take().is_some());
+        app.run_prompt_action(PromptAction::SaveAs, "u.txt")
+            .unwrap();
+        assert_eq!(names(&app), ["a.txt", "b.txt"]);
+        assert_eq!(app.current_tab, 1);
+
+        app.tabs[0].editor.cursor = Position::new(1, 0);
+        app.close_tab(0);
+        app.close_other_tabs();
+        assert_eq!(names(&app), ["b.txt"]);
+        app.close_tab(0);
+        assert_eq!(names(&app), ["Untitled"]);
+
+        app.reopen_closed_tab();
+        app.reopen_closed_tab();
+        assert_eq!(names(&app), ["Untitled", "b.txt", "a.txt"]);
+        assert_eq!(app.tabs[2].editor.cursor, Position::new(1, 0));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tab_labels_disambiguate_duplicate_names() {
+        let mut app = App::new().unwrap();
+        for path in ["/p/src/editor/mod.rs", "/p/src/ui/mod.rs", "/p/main.rs"] {
+            app.open_file(PathBuf::from(path)).unwrap();
+        }
+        app.open_file(PathBuf::from("/q/src/ui/mod.rs")).unwrap();
+        let sep = std::path::MAIN_SEPARATOR;
+        assert_eq!(
+            app.tab_labels(),
+            [
+                "Untitled".to_string(),
+                format!("editor{sep}mod.rs"),
+                format!("p{sep}…{sep}mod.rs"),
+                "main.rs".to_string(),
+                format!("q{sep}…{sep}mod.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_panes_share_the_buffer() {
+        let mut app = App::new().unwrap();
+        app.paste_text("one\ntwo\n");
+        app.split_pane(Direction::Horizontal);
+        let first = app.panes.ids()[0];
+        assert_eq!(app.panes.len(), 2);
+
+        // Typing in the new pane shows up in the first one, which keeps its own cursor
+        app.handle_input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        app.handle_input(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        let second = app.panes.focused();
+        app.focus_pane(first);
+        let tab = app.get_current_tab().unwrap();
+        assert_eq!(buffer(&app, 0).content.to_string(), "one\ntwo\n!");
+        assert_eq!(tab.editor.cursor, Position::new(2, 0));
+        assert_eq!(app.panes.get_mut(second).unwrap().editor.cursor.row, 1);
+
+        // Closing the tab shown in the other pane points it at the remaining tab
+        app.new_file();
+        app.close_tab(0);
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 'd');
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.panes.get_mut(second).unwrap().tab, 0);
+        assert_eq!(app.buffers.iter().count(), 1);
+
+        app.close_pane();
+        assert_eq!(app.panes.len(), 1);
+        assert_eq!(app.panes.focused(), second);
+    }
+
+    #[test]
+    fn test_views_share_one_buffer_per_file() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_buffers_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let mut app = App::new().unwrap();
+        app.open_file(path.clone()).unwrap();
+        app.split_pane(Direction::Vertical);
+        let other = app.panes.ids()[0];
+        for _ in 0..3 {
+            app.handle_input(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        }
+
+        // Opening the file again, under any spelling, shows the same buffer
+        app.new_file();
+        app.open_file(dir.join(".").join("a.txt")).unwrap();
+        assert_eq!(app.current_tab, 1);
+        assert_eq!(app.buffers.iter().count(), 3);
+
+        // Saving through one view marks the buffer saved in all of them
+        app.handle_input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        app.save_current_file().unwrap();
+        app.focus_pane(other);
+        let buffer = app.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.to_string(), "one\ntwo\nthree\n!");
+        assert!(!buffer.modified);
+
+        // Reloading a shorter file keeps the other view inside it
+        std::fs::write(&path, "x\n").unwrap();
+        app.check_tabs_on_disk();
+        assert_eq!(
+            app.panes
+                .get_mut(app.panes.ids()[1])
+                .unwrap()
+                .editor
+                .cursor
+                .row,
+            1
+        );
+
+        app.close_tab(1);
+        assert_eq!(app.buffers.find(&path), None);
+        assert_eq!(app.buffers.iter().count(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_search_selects_matches_as_you_type() {
+        let mut app = App::new().unwrap();
+        app.paste_text("let x = Foo;\nfoo(x);\nfood\n");
+        app.tabs[0].editor.cursor = Position::new(0, 5);
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.open_search();
+        for c in "foo".chars() {
+            app.handle_search_input(key(KeyCode::Char(c)));
+        }
+        let search = app.search.as_ref().unwrap();
+        assert_eq!(search.matches.len(), 3);
+        assert_eq!(search.counter(), "1 of 3");
+        let tab = &app.tabs[0];
+        assert_eq!(
+            tab.editor
+                .selected_text(&buffer(&app, 0).content)
+                .as_deref(),
+            Some("Foo")
+        );
+
+        app.handle_search_input(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT));
+        app.handle_search_input(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::ALT));
+        assert_eq!(app.search.as_ref().unwrap().counter(), "1 of 1");
+        assert_eq!(app.tabs[0].editor.cursor, Position::new(1, 3));
+
+        app.handle_search_input(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::ALT));
+        app.handle_search_input(key(KeyCode::Enter));
+        app.handle_search_input(key(KeyCode::Up));
+        app.handle_search_input(key(KeyCode::Up));
+        assert_eq!(app.search.as_ref().unwrap().counter(), "2 of 2");
+        assert_eq!(app.tabs[0].editor.cursor, Position::new(2, 3));
+
+        app.handle_search_input(key(KeyCode::Char('x')));
+        assert_eq!(app.search.as_ref().unwrap().counter(), "No matches");
+        assert_eq!(
+            app.tabs[0].editor.selection_range(&buffer(&app, 0).content),
+            None
+        );
+        app.handle_search_input(key(KeyCode::Esc));
+        assert!(app.search.is_none());
+    }
+
+    #[test]
+    fn test_open_and_save_preserve_encoding_and_line_endings() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_format_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("latin1.txt");
+        std::fs::write(&path, b"caf\xe9\r\nna\xefve\r\n").unwrap();
+
+        let mut app = App::new().unwrap();
+        app.open_file(path.clone()).unwrap();
+        let buffer = app.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.to_string(), "caf\u{e9}\nna\u{ef}ve\n");
+        assert_eq!(
+            (buffer.encoding, buffer.line_ending),
+            (Encoding::Latin1, LineEnding::CrLf)
+        );
+
+        app.handle_input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        app.save_current_file().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"!caf\xe9\r\nna\xefve\r\n");
+
+        // Latin-1 -> UTF-8 and CRLF -> CR -> LF
+        app.cycle_encoding();
+        app.cycle_line_ending();
+        app.cycle_line_ending();
+        assert!(app.get_current_buffer().unwrap().modified);
+        app.save_current_file().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "!caf\u{e9}\nna\u{ef}ve\n"
+        );
+
+        // Mixed line endings are unified on save, which opening the file warns about
+        let path = dir.join("mixed.txt");
+        std::fs::write(&path, "a\nb\rc\n").unwrap();
+        app.open_file(path.clone()).unwrap();
+        assert!(!app.get_current_buffer().unwrap().modified);
+        assert!(app
+            .status_message
+            .as_ref()
+            .unwrap()
+            .contains("mixed line endings"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_file_handles_missing_binary_and_directory_paths() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_open_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut app = App::new().unwrap();
+
+        app.open_file(dir.join("new.txt")).unwrap();
+        let buffer = app.get_current_buffer().unwrap();
+        assert!(buffer.is_new);
+        assert_eq!(buffer.content.len_chars(), 0);
+        app.save_current_file().unwrap();
+        assert!(!app.get_current_buffer().unwrap().is_new);
+        assert!(dir.join("new.txt").exists());
+
+        assert!(app.open_file(&dir).is_err());
+        assert_eq!(app.tabs.len(), 2);
+
+        std::fs::write(dir.join("data.bin"), b"\x7fELF\0\0").unwrap();
+        app.open_file(dir.join("data.bin")).unwrap();
+        assert_eq!(app.tabs.len(), 2);
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 'y');
+        assert_eq!(app.tabs.len(), 3);
+        assert_eq!(app.get_current_buffer().unwrap().content.len_chars(), 6);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_external_changes_reload_or_merge() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_external_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let mut app = App::new().unwrap();
+        app.open_file(path.clone()).unwrap();
+
+        // Unmodified tabs follow the file
+        std::fs::write(&path, "one\ntwo\nthree\nfour\n").unwrap();
+        app.check_tabs_on_disk();
+        let buffer = app.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.to_string(), "one\ntwo\nthree\nfour\n");
+        assert!(!buffer.modified);
+
+        // Modified tabs ask, and saving does not clobber the file
+        app.handle_input(KeyEvent::new(KeyCode::Char('1'), KeyModifiers::NONE));
+        std::fs::write(&path, "one\ntwo\n3\nfour\n").unwrap();
+        app.save_current_file().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "one\ntwo\n3\nfour\n"
+        );
+        let confirm = app.confirm.take().unwrap();
+        assert_eq!(
+            confirm.action,
+            ConfirmAction::ExternalChange { path: path.clone() }
+        );
+
+        app.run_confirm_action(confirm.action, 'm');
+        let buffer = app.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.to_string(), "1one\ntwo\n3\nfour\n");
+        assert!(buffer.modified);
+        app.save_current_file().unwrap();
+        assert!(app.confirm.is_none());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1one\ntwo\n3\nfour\n"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_swap_files_are_written_and_recovered() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_recover_{}", std::process::id()));
+        let swap_dir = dir.join("swap");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "saved\n").unwrap();
+        let swap_files = || {
+            std::fs::read_dir(&swap_dir)
+                .unwrap()
+                .filter(|e| e.as_ref().unwrap().path().extension().unwrap() == "swp")
+                .count()
+        };
+
+        let mut app = App::new().unwrap();
+        app.swap = Some(SwapStore::new(swap_dir.clone()).unwrap());
+        app.handle_input(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+        app.open_file(path.clone()).unwrap();
+        app.handle_input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        app.write_swap_files();
+        assert_eq!(swap_files(), 2);
+
+        // Pretend the editor crashed, leaving its swap files behind
+        for entry in std::fs::read_dir(&swap_dir).unwrap() {
+            let swap = entry.unwrap().path();
+            if swap.extension().unwrap() != "swp" {
+                continue;
+            }
+            let text = std::fs::read_to_string(&swap).unwrap();
+            let pid = format!("pid: {}", std::process::id());
+            std::fs::write(&swap, text.replace(&pid, "pid: 4294967295")).unwrap();
+            let name = swap.file_name().unwrap().to_string_lossy();
+            let name = name.replace(&std::process::id().to_string(), "4294967295");
+            std::fs::rename(&swap, swap_dir.join(name)).unwrap();
+        }
+
+        let mut app = App::new().unwrap();
+        app.swap = Some(SwapStore::new(swap_dir.clone()).unwrap());
+        app.open_file(path.clone()).unwrap();
+        let confirm = app.confirm.take().unwrap();
+        assert_eq!(confirm.choices.len(), 3);
+        app.run_confirm_action(confirm.action, 'd');
+        assert!(buffer(&app, 2).content.to_string().contains("+!saved"));
+
+        // The question comes back after the diff, and editing meanwhile keeps the leftover
+        app.current_tab = 1;
+        app.handle_input(KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE));
+        app.write_swap_files();
+        assert_eq!(swap_files(), 3);
+        app.tick();
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 'r');
+        assert_eq!(buffer(&app, 1).content.to_string(), "!saved\n");
+        assert!(buffer(&app, 1).modified);
+
+        app.find_leftover_swap_files();
+        let confirm = app.confirm.take().unwrap();
+        app.run_confirm_action(confirm.action, 'r');
+        assert_eq!(app.get_current_buffer().unwrap().content.to_string(), "u");
+        assert_eq!(swap_files(), 1);
+
+        app.write_swap_files();
+        app.remove_swap_files();
+        assert_eq!(swap_files(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_restores_tabs_cursors_and_explorer() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_restore_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(dir.join("src").join("b.txt"), "b\n").unwrap();
+
+        let mut app = App::new().unwrap();
+        app.set_directory(&dir).unwrap();
+        app.open_file(dir.join("a.txt")).unwrap();
+        app.tabs[1].editor.cursor = Position::new(2, 3);
+        app.open_file(dir.join("src").join("b.txt")).unwrap();
+        app.file_explorer.select_path(&dir.join("src"));
+        app.file_explorer.expand_selected().unwrap();
+        app.current_tab = 1;
+        app.show_file_explorer = false;
+        let mut session = app.session();
+        assert_eq!(session.tabs.len(), 2);
+        assert_eq!(session.current_tab, 0);
+
+        // Deleted files are skipped and cursors outside the file are clamped
+        session.tabs[0].cursor = Position::new(9, 9);
+        std::fs::remove_file(dir.join("src").join("b.txt")).unwrap();
+        let mut app = App::new().unwrap();
+        app.set_directory(&dir).unwrap();
+        app.restore_session(session);
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.current_tab, 0);
+        assert_eq!(buffer(&app, 0).name, "a.txt");
+        assert_eq!(app.tabs[0].editor.cursor, Position::new(3, 0));
+        assert!(!app.show_file_explorer);
+        assert!(app
+            .file_explorer
+            .expanded_paths()
+            .contains(&dir.join("src")));
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,221 @@
+use anyhow::{anyhow, bail, Result};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Where copied text is sent to (and pasted text read from) besides the internal register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardProvider {
+    /// Only the editor's own register.
+    Internal,
+    /// Terminal escape sequence; works over SSH and inside tmux, but is write-only.
+    Osc52,
+    /// External programs such as `xclip`, `wl-copy` or `pbcopy`.
+    Command {
+        copy: Vec<String>,
+        paste: Option<Vec<String>>,
+    },
+}
+
+impl ClipboardProvider {
+    /// Pick a provider from the CLI options, detecting one from the environment for "auto".
+    pub fn from_options(
+        kind: &str,
+        copy_command: Option<&str>,
+        paste_command: Option<&str>,
+    ) -> Result<Self> {
+        let split = |cmd: &str| {
+            cmd.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        match kind {
+            "internal" => Ok(Self::Internal),
+            "osc52" => Ok(Self::Osc52),
+            "command" => {
+                let copy = copy_command
+                    .map(split)
+                    .filter(|c| !c.is_empty())
+                    .ok_or_else(|| anyhow!("--clipboard command requires --copy-command"))?;
+                Ok(Self::Command {
+                    copy,
+                    paste: paste_command.map(split).filter(|c| !c.is_empty()),
+                })
+            }
+            "auto" => match copy_command {
+                Some(copy) => Self::from_options("command", Some(copy), paste_command),
+                None => Ok(Self::detect()),
+            },
+            other => bail!("Unknown clipboard provider: {other}"),
+        }
+    }
+
+    /// Detect a provider for the current session.
+    fn detect() -> Self {
+        let remote =
+            std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+        if remote {
+            return Self::Osc52;
+        }
+
+        let candidates: [(&str, &[&str], &[&str]); 3] = [
+            (
+                "WAYLAND_DISPLAY",
+                &["wl-copy"],
+                &["wl-paste", "--no-newline"],
+            ),
+            (
+                "DISPLAY",
+                &["xclip", "-selection", "clipboard"],
+                &["xclip", "-selection", "clipboard", "-o"],
+            ),
+            ("", &["pbcopy"], &["pbpaste"]),
+        ];
+        for (env, copy, paste) in candidates {
+            if (env.is_empty() || std::env::var_os(env).is_some()) && in_path(copy[0]) {
+                let to_vec = |args: &[&str]| args.iter().map(|a| a.to_string()).collect();
+                return Self::Command {
+                    copy: to_vec(copy),
+                    paste: Some(to_vec(paste)),
+                };
+            }
+        }
+
+        Self::Osc52
+    }
+}
+
+/// Clipboard with an internal register that is always kept up to date, so pasting
+/// works even when the provider cannot be read back from.
+pub struct Clipboard {
+    pub provider: ClipboardProvider,
+    register: String,
+}
+
+impl Clipboard {
+    pub fn new(provider: ClipboardProvider) -> Self {
+        Self {
+            provider,
+            register: String::new(),
+        }
+    }
+
+    /// Copy text to the register and the provider.
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        self.register = text.to_string();
+        match &self.provider {
+            ClipboardProvider::Internal => Ok(()),
+            ClipboardProvider::Osc52 => {
+                let mut stdout = io::stdout();
+                stdout.write_all(
+                    osc52_sequence(text, std::env::var_os("TMUX").is_some()).as_bytes(),
+                )?;
+                stdout.flush()?;
+                Ok(())
+            }
+            ClipboardProvider::Command { copy, .. } => run_copy_command(copy, text),
+        }
+    }
+
+    /// Text to paste: the provider's contents if readable, otherwise the register.
+    pub fn paste(&mut self) -> String {
+        if let ClipboardProvider::Command {
+            paste: Some(paste), ..
+        } = &self.provider
+        {
+            if let Ok(text) = run_paste_command(paste) {
+                return text;
+            }
+        }
+        self.register.clone()
+    }
+}
+
+fn run_copy_command(command: &[String], text: &str) -> Result<()> {
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} exited with {status}", command[0]);
+    }
+    Ok(())
+}
+
+fn run_paste_command(command: &[String]) -> Result<String> {
+    let output = Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("{} exited with {}", command[0], output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Build the OSC 52 sequence that sets the system clipboard, wrapped in a
+/// DCS passthrough when running inside tmux.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence_encodes_text() {
+        assert_eq!(base64_encode(b"hello"), "aGVsbG8=");
+        assert_eq!(base64_encode(b"hi!"), "aGkh");
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            osc52_sequence("hi", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_internal_clipboard_round_trip() {
+        let mut clipboard = Clipboard::new(ClipboardProvider::Internal);
+        clipboard.copy("line one\nline two").unwrap();
+        assert_eq!(clipboard.paste(), "line one\nline two");
+    }
+}
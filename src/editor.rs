@@ -1,8 +1,11 @@
+use crate::history::{Edit, EditKind, History};
+use crate::text;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ropey::Rope;
+use serde::{Deserialize, Serialize};
 use std::cmp;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Position {
     pub row: usize,
     pub col: usize,
@@ -14,10 +17,29 @@ impl Position {
     }
 }
 
+/// How Tab indents: with a tab character or with spaces up to the next multiple of `width`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indent {
+    pub hard_tabs: bool,
+    pub width: usize,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self {
+            hard_tabs: false,
+            width: 4,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Editor {
     pub cursor: Position,
     pub scroll_offset: Position,
+    /// The fixed end of the selection; the cursor is the moving end.
+    pub anchor: Option<Position>,
+    pub indent: Indent,
 }
 
 impl Editor {
@@ -25,27 +47,52 @@ impl Editor {
         Self {
             cursor: Position::new(0, 0),
             scroll_offset: Position::new(0, 0),
+            anchor: None,
+            indent: Indent::default(),
         }
     }
 
-    pub fn handle_input(&mut self, key: KeyEvent, content: &mut Rope) {
+    pub fn handle_input(&mut self, key: KeyEvent, content: &mut Rope, history: &mut History) {
         match key.code {
             KeyCode::Char(c) => {
                 if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.handle_ctrl_char(c, content);
+                    self.handle_ctrl_char(c, content, history);
                 } else {
-                    self.insert_char(c, content);
+                    self.insert_char(c, content, history);
                 }
             }
             KeyCode::Backspace => {
-                self.delete_char(content);
+                self.delete_char(content, history);
             }
             KeyCode::Delete => {
-                self.delete_char_forward(content);
+                self.delete_char_forward(content, history);
             }
             KeyCode::Enter => {
-                self.insert_newline(content);
+                self.insert_newline(content, history);
+            }
+            KeyCode::Tab => {
+                self.indent(content, history);
+            }
+            KeyCode::BackTab => {
+                self.outdent(content, history);
+            }
+            _ => {
+                history.break_group();
+                self.handle_movement(key, content);
+            }
+        }
+    }
+
+    fn handle_movement(&mut self, key: KeyEvent, content: &Rope) {
+        if key.modifiers.contains(KeyModifiers::SHIFT) {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
             }
+        } else {
+            self.anchor = None;
+        }
+
+        match key.code {
             KeyCode::Up => {
                 self.move_cursor_up(content);
             }
@@ -74,83 +121,266 @@ impl Editor {
         }
     }
 
-    fn handle_ctrl_char(&mut self, c: char, content: &mut Rope) {
+    fn handle_ctrl_char(&mut self, c: char, content: &mut Rope, history: &mut History) {
         match c {
             'a' => self.select_all(content),
-            'c' => self.copy_selection(content),
-            'v' => self.paste(content),
-            'x' => self.cut_selection(content),
-            'z' => self.undo(),
-            'y' => self.redo(),
+            'z' => self.undo(content, history),
+            'y' => self.redo(content, history),
             _ => {}
         }
     }
 
-    fn insert_char(&mut self, c: char, content: &mut Rope) {
-        let char_idx = self.get_char_index(content);
-        content.insert_char(char_idx, c);
-        self.cursor.col += 1;
+    /// Replace the chars in `start..end` with `text`, record the change in `history`
+    /// and leave the cursor after the inserted text.
+    fn replace(
+        &mut self,
+        content: &mut Rope,
+        history: &mut History,
+        start: usize,
+        end: usize,
+        text: &str,
+        kind: EditKind,
+    ) {
+        let cursor_before = self.cursor;
+        let removed = content.slice(start..end).to_string();
+        content.remove(start..end);
+        content.insert(start, text);
+        self.cursor = char_to_position(content, start + text.chars().count());
+        let edit = Edit {
+            char_idx: start,
+            removed,
+            inserted: text.to_string(),
+        };
+        history.record(edit, kind, cursor_before, self.cursor);
+    }
+
+    /// Remove the selected text, if any. Returns whether anything was selected.
+    fn delete_selection(&mut self, content: &mut Rope, history: &mut History) -> bool {
+        match self.selection_range(content) {
+            Some((start, end)) => {
+                self.anchor = None;
+                self.replace(content, history, start, end, "", EditKind::Other);
+                true
+            }
+            None => {
+                self.anchor = None;
+                false
+            }
+        }
     }
 
-    fn insert_newline(&mut self, content: &mut Rope) {
+    fn insert_char(&mut self, c: char, content: &mut Rope, history: &mut History) {
+        let mut buf = [0; 4];
+        let text = c.encode_utf8(&mut buf);
+        if let Some((start, end)) = self.selection_range(content) {
+            self.anchor = None;
+            self.replace(content, history, start, end, text, EditKind::Other);
+            return;
+        }
+        self.anchor = None;
         let char_idx = self.get_char_index(content);
-        content.insert_char(char_idx, '\n');
-        self.cursor.row += 1;
-        self.cursor.col = 0;
+        self.replace(content, history, char_idx, char_idx, text, EditKind::Insert);
     }
 
-    fn delete_char(&mut self, content: &mut Rope) {
-        if self.cursor.col > 0 {
+    fn insert_newline(&mut self, content: &mut Rope, history: &mut History) {
+        let (start, end) = self.selection_range(content).unwrap_or_else(|| {
             let char_idx = self.get_char_index(content);
-            content.remove(char_idx - 1..char_idx);
-            self.cursor.col -= 1;
-        } else if self.cursor.row > 0 {
-            // Join with previous line
-            let line_start = content.line_to_char(self.cursor.row);
-            let prev_line_len = content.line(self.cursor.row - 1).len_chars();
+            (char_idx, char_idx)
+        });
+        self.anchor = None;
+        self.replace(content, history, start, end, "\n", EditKind::Other);
+    }
 
-            content.remove(line_start - 1..line_start);
-            self.cursor.row -= 1;
-            self.cursor.col = prev_line_len;
+    /// Indent every line touched by a multi-line selection, or insert one indent at the cursor.
+    fn indent(&mut self, content: &mut Rope, history: &mut History) {
+        if self.selected_rows(content).is_some() {
+            let indent = self.indent_unit(0);
+            self.change_line_starts(content, history, |line| {
+                if line.is_empty() {
+                    (String::new(), 0)
+                } else {
+                    (indent.clone(), 0)
+                }
+            });
+            return;
+        }
+
+        let (start, end) = self.selection_range(content).unwrap_or_else(|| {
+            let char_idx = self.get_char_index(content);
+            (char_idx, char_idx)
+        });
+        self.anchor = None;
+        let col = char_to_position(content, start).col;
+        let indent = self.indent_unit(col);
+        history.break_group();
+        self.replace(content, history, start, end, &indent, EditKind::Other);
+    }
+
+    /// Remove one level of indentation from the current line or every selected line.
+    fn outdent(&mut self, content: &mut Rope, history: &mut History) {
+        let width = self.indent.width.max(1);
+        self.change_line_starts(content, history, |line| {
+            let removed = if line.starts_with('\t') {
+                1
+            } else {
+                line.chars().take(width).take_while(|&c| c == ' ').count()
+            };
+            (String::new(), removed)
+        });
+    }
+
+    /// Text inserted by one Tab press at column `col`.
+    fn indent_unit(&self, col: usize) -> String {
+        if self.indent.hard_tabs {
+            "\t".to_string()
+        } else {
+            let width = self.indent.width.max(1);
+            " ".repeat(width - col % width)
+        }
+    }
+
+    /// First and last row of a selection that spans several lines. A selection ending at
+    /// the start of a line does not include that line.
+    fn selected_rows(&self, content: &Rope) -> Option<(usize, usize)> {
+        let (start, end) = self.selection_range(content)?;
+        let first = char_to_position(content, start).row;
+        let end = char_to_position(content, end);
+        let last = if end.col == 0 { end.row - 1 } else { end.row };
+        (last > first).then_some((first, last))
+    }
+
+    /// Rewrite the start of the current line, or of every selected line, as one undo step.
+    /// `change` maps a line to the text to insert at its start and the number of chars to
+    /// remove there; the cursor and the selection keep their place in the text.
+    fn change_line_starts(
+        &mut self,
+        content: &mut Rope,
+        history: &mut History,
+        change: impl Fn(&str) -> (String, usize),
+    ) {
+        let (first, last) = self.selected_rows(content).unwrap_or_else(|| {
+            let row = cmp::min(self.cursor.row, content.len_lines().saturating_sub(1));
+            (row, row)
+        });
+        let start = content.line_to_char(first);
+        let end = content.line_to_char(last) + text::line_len(content, last);
+        let old = content.slice(start..end).to_string();
+
+        let mut changes = Vec::new();
+        let mut new = String::new();
+        for row in first..=last {
+            let line = text::line_content(content, row);
+            let (inserted, removed) = change(&line);
+            changes.push((inserted.chars().count(), removed));
+            new.push_str(&inserted);
+            new.extend(line.chars().skip(removed));
+            if row < last {
+                let line_break = content.line(row).slice(text::line_len(content, row)..);
+                new.extend(line_break.chars());
+            }
+        }
+        if new == old {
+            return;
+        }
+
+        let adjust = |position: Position| {
+            let Some(&(inserted, removed)) =
+                position.row.checked_sub(first).and_then(|i| changes.get(i))
+            else {
+                return position;
+            };
+            // A selection starting at column 0 keeps covering whole lines
+            let col = match position.col {
+                0 => 0,
+                col => col.saturating_sub(removed) + inserted,
+            };
+            Position::new(position.row, col)
+        };
+        let cursor = adjust(self.cursor);
+        let anchor = self.anchor.map(adjust);
+
+        history.break_group();
+        self.replace(content, history, start, end, &new, EditKind::Other);
+        history.break_group();
+        self.cursor = cursor;
+        self.anchor = anchor;
+    }
+
+    fn delete_char(&mut self, content: &mut Rope, history: &mut History) {
+        if self.delete_selection(content, history) {
+            return;
+        }
+        let char_idx = self.get_char_index(content);
+        if char_idx > 0 {
+            // At column 0 this joins the line with the previous one, removing the whole
+            // line break even if it is a CRLF pair
+            let start = match self.cursor.col {
+                0 => {
+                    char_idx
+                        - (content.line(self.cursor.row - 1).len_chars()
+                            - text::line_len(content, self.cursor.row - 1))
+                }
+                _ => char_idx - 1,
+            };
+            self.replace(
+                content,
+                history,
+                start,
+                char_idx,
+                "",
+                EditKind::DeleteBackward,
+            );
         }
     }
 
-    fn delete_char_forward(&mut self, content: &mut Rope) {
+    fn delete_char_forward(&mut self, content: &mut Rope, history: &mut History) {
+        if self.delete_selection(content, history) {
+            return;
+        }
         let char_idx = self.get_char_index(content);
         if char_idx < content.len_chars() {
-            content.remove(char_idx..char_idx + 1);
+            let end = if self.cursor.col >= text::line_len(content, self.cursor.row) {
+                content.line_to_char(self.cursor.row + 1)
+            } else {
+                char_idx + 1
+            };
+            self.replace(content, history, char_idx, end, "", EditKind::DeleteForward);
         }
     }
 
     fn move_cursor_up(&mut self, content: &Rope) {
         if self.cursor.row > 0 {
-            self.cursor.row -= 1;
-            let line_len = content.line(self.cursor.row).len_chars();
-            self.cursor.col = cmp::min(self.cursor.col, line_len);
+            self.move_to_row(self.cursor.row - 1, content);
         }
     }
 
     fn move_cursor_down(&mut self, content: &Rope) {
         if self.cursor.row < content.len_lines().saturating_sub(1) {
-            self.cursor.row += 1;
-            let line_len = content.line(self.cursor.row).len_chars();
-            self.cursor.col = cmp::min(self.cursor.col, line_len);
+            self.move_to_row(self.cursor.row + 1, content);
         }
     }
 
+    /// Move the cursor to `row`, keeping its display column as far as the line allows.
+    fn move_to_row(&mut self, row: usize, content: &Rope) {
+        let column = self.cursor_column(content);
+        self.cursor.row = row;
+        self.cursor.col = self.col_at_column(content, row, column);
+    }
+
     fn move_cursor_left(&mut self, content: &Rope) {
         if self.cursor.col > 0 {
-            self.cursor.col -= 1;
+            let line = text::line_content(content, self.cursor.row);
+            self.cursor.col = text::prev_grapheme(&line, self.cursor.col);
         } else if self.cursor.row > 0 {
             self.cursor.row -= 1;
-            self.cursor.col = content.line(self.cursor.row).len_chars();
+            self.cursor.col = text::line_len(content, self.cursor.row);
         }
     }
 
     fn move_cursor_right(&mut self, content: &Rope) {
-        let line_len = content.line(self.cursor.row).len_chars();
-        if self.cursor.col < line_len {
-            self.cursor.col += 1;
+        let line = text::line_content(content, self.cursor.row);
+        if self.cursor.col < line.chars().count() {
+            self.cursor.col = text::next_grapheme(&line, self.cursor.col);
         } else if self.cursor.row < content.len_lines().saturating_sub(1) {
             self.cursor.row += 1;
             self.cursor.col = 0;
@@ -162,7 +392,7 @@ impl Editor {
     }
 
     fn move_to_line_end(&mut self, content: &Rope) {
-        self.cursor.col = content.line(self.cursor.row).len_chars();
+        self.cursor.col = text::line_len(content, self.cursor.row);
     }
 
     fn page_up(&mut self, content: &Rope) {
@@ -179,33 +409,125 @@ impl Editor {
         }
     }
 
-    fn get_char_index(&self, content: &Rope) -> usize {
-        content.line_to_char(self.cursor.row) + self.cursor.col
+    /// Char index of the cursor in `content`.
+    pub fn get_char_index(&self, content: &Rope) -> usize {
+        position_to_char(content, self.cursor)
+    }
+
+    /// The selected range as ordered char indices, or None if nothing is selected.
+    pub fn selection_range(&self, content: &Rope) -> Option<(usize, usize)> {
+        let anchor = position_to_char(content, self.anchor?);
+        let head = position_to_char(content, self.cursor);
+        match anchor.cmp(&head) {
+            cmp::Ordering::Less => Some((anchor, head)),
+            cmp::Ordering::Greater => Some((head, anchor)),
+            cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn select_all(&mut self, content: &Rope) {
+        self.anchor = Some(Position::new(0, 0));
+        self.cursor = char_to_position(content, content.len_chars());
+    }
+
+    /// Select the chars `start..end` of `content`, with the cursor at the end.
+    pub fn select_chars(&mut self, start: usize, end: usize, content: &Rope) {
+        self.anchor = Some(char_to_position(content, start.min(content.len_chars())));
+        self.cursor = char_to_position(content, end.min(content.len_chars()));
+    }
+
+    /// The currently selected text, if any.
+    pub fn selected_text(&self, content: &Rope) -> Option<String> {
+        let (start, end) = self.selection_range(content)?;
+        Some(content.slice(start..end).to_string())
+    }
+
+    /// Remove the selected text and return it.
+    pub fn cut_selection(&mut self, content: &mut Rope, history: &mut History) -> Option<String> {
+        let text = self.selected_text(content)?;
+        self.delete_selection(content, history);
+        Some(text)
+    }
+
+    /// Replace the whole content with `text` as a single undo step, keeping the cursor
+    /// close to where it was.
+    pub fn replace_all(&mut self, text: &str, content: &mut Rope, history: &mut History) {
+        let cursor = self.cursor;
+        self.anchor = None;
+        history.break_group();
+        self.replace(
+            content,
+            history,
+            0,
+            content.len_chars(),
+            text,
+            EditKind::Other,
+        );
+        history.break_group();
+        self.cursor = char_to_position(content, position_to_char(content, cursor));
     }
 
-    // Placeholder methods for advanced features
-    fn select_all(&mut self, _content: &Rope) {
-        // TODO: Implement selection
+    /// Insert `text` at the cursor, replacing any selection, as a single undo step.
+    /// The cursor ends up after the inserted text.
+    pub fn insert_text(&mut self, text: &str, content: &mut Rope, history: &mut History) {
+        let (start, end) = self.selection_range(content).unwrap_or_else(|| {
+            let char_idx = self.get_char_index(content);
+            (char_idx, char_idx)
+        });
+        self.anchor = None;
+        history.break_group();
+        self.replace(content, history, start, end, text, EditKind::Other);
     }
 
-    fn copy_selection(&mut self, _content: &Rope) {
-        // TODO: Implement copy
+    fn undo(&mut self, content: &mut Rope, history: &mut History) {
+        if let Some(cursor) = history.undo(content) {
+            self.anchor = None;
+            self.cursor = cursor;
+        }
     }
 
-    fn paste(&mut self, _content: &mut Rope) {
-        // TODO: Implement paste
+    fn redo(&mut self, content: &mut Rope, history: &mut History) {
+        if let Some(cursor) = history.redo(content) {
+            self.anchor = None;
+            self.cursor = cursor;
+        }
     }
 
-    fn cut_selection(&mut self, _content: &mut Rope) {
-        // TODO: Implement cut
+    /// Place the cursor at `position`, clamped to the content.
+    /// With `extend`, the selection is extended instead of cleared.
+    pub fn move_to(&mut self, position: Position, content: &Rope, extend: bool) {
+        if !extend {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
+        self.cursor = char_to_position(content, position_to_char(content, position));
     }
 
-    fn undo(&mut self) {
-        // TODO: Implement undo
+    /// Move the cursor and selection anchor back inside `content`, e.g. after the text was
+    /// edited through another view.
+    pub fn clamp_to(&mut self, content: &Rope) {
+        self.cursor = char_to_position(content, position_to_char(content, self.cursor));
+        self.anchor = self
+            .anchor
+            .map(|anchor| char_to_position(content, position_to_char(content, anchor)));
     }
 
-    fn redo(&mut self) {
-        // TODO: Implement redo
+    /// Scroll the view by `delta` lines, keeping the cursor inside the `height` visible rows.
+    pub fn scroll_by(&mut self, delta: isize, content: &Rope, height: usize) {
+        let max_row = content.len_lines().saturating_sub(1);
+        self.scroll_offset.row = self
+            .scroll_offset
+            .row
+            .saturating_add_signed(delta)
+            .min(max_row);
+
+        let last_visible = self.scroll_offset.row + height.saturating_sub(1);
+        let row = self.cursor.row.clamp(self.scroll_offset.row, last_visible);
+        if row != self.cursor.row {
+            let target = Position::new(row, self.cursor.col);
+            self.cursor = char_to_position(content, position_to_char(content, target));
+        }
     }
 
     pub fn get_visible_lines(&self, content: &Rope, height: usize) -> Vec<String> {
@@ -213,11 +535,35 @@ impl Editor {
         let end_line = cmp::min(start_line + height, content.len_lines());
 
         (start_line..end_line)
-            .map(|i| content.line(i).to_string())
+            .map(|i| text::line_content(content, i))
             .collect()
     }
 
-    pub fn ensure_cursor_visible(&mut self, _content: &Rope, width: usize, height: usize) {
+    /// Display column of the cursor, with tabs expanded and wide chars counted twice.
+    pub fn cursor_column(&self, content: &Rope) -> usize {
+        if self.cursor.row >= content.len_lines() {
+            return 0;
+        }
+        let line = text::line_content(content, self.cursor.row);
+        text::display_column(&line, self.cursor.col, self.indent.width)
+    }
+
+    /// Char column of the text shown at display column `column` of `row`.
+    pub fn col_at_column(&self, content: &Rope, row: usize, column: usize) -> usize {
+        if row >= content.len_lines() {
+            return 0;
+        }
+        let line = text::line_content(content, row);
+        text::col_at_display(&line, column, self.indent.width)
+    }
+
+    /// Keep the cursor inside the `width` x `height` view; the horizontal scroll offset is
+    /// measured in display columns.
+    pub fn ensure_cursor_visible(&mut self, content: &Rope, width: usize, height: usize) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
         // Ensure cursor is within visible area
         if self.cursor.row < self.scroll_offset.row {
             self.scroll_offset.row = self.cursor.row;
@@ -225,14 +571,29 @@ impl Editor {
             self.scroll_offset.row = self.cursor.row.saturating_sub(height - 1);
         }
 
-        if self.cursor.col < self.scroll_offset.col {
-            self.scroll_offset.col = self.cursor.col;
-        } else if self.cursor.col >= self.scroll_offset.col + width {
-            self.scroll_offset.col = self.cursor.col.saturating_sub(width - 1);
+        let column = self.cursor_column(content);
+        if column < self.scroll_offset.col {
+            self.scroll_offset.col = column;
+        } else if column >= self.scroll_offset.col + width {
+            self.scroll_offset.col = column.saturating_sub(width - 1);
         }
     }
 }
 
+/// Convert a char index into a row/column position.
+fn char_to_position(content: &Rope, char_idx: usize) -> Position {
+    let row = content.char_to_line(char_idx);
+    Position::new(row, char_idx - content.line_to_char(row))
+}
+
+/// Convert a row/column position into a char index, clamped to the content
+/// of the line (not counting its line break).
+fn position_to_char(content: &Rope, position: Position) -> usize {
+    let row = cmp::min(position.row, content.len_lines().saturating_sub(1));
+    let line_start = content.line_to_char(row);
+    line_start + cmp::min(position.col, text::line_len(content, row))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +606,169 @@ mod tests {
         assert_eq!(editor.scroll_offset.row, 0);
         assert_eq!(editor.scroll_offset.col, 0);
     }
+
+    #[test]
+    fn test_editor_undo_redo_restores_content_and_cursor() {
+        let mut editor = Editor::new();
+        let mut content = Rope::new();
+        let mut history = History::new();
+        for c in "hi".chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            editor.handle_input(key, &mut content, &mut history);
+        }
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        editor.handle_input(enter, &mut content, &mut history);
+        assert_eq!(content.to_string(), "hi\n");
+        assert_eq!(editor.cursor, Position::new(1, 0));
+
+        let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        editor.handle_input(undo, &mut content, &mut history);
+        assert_eq!(content.to_string(), "hi");
+        assert_eq!(editor.cursor, Position::new(0, 2));
+        editor.handle_input(undo, &mut content, &mut history);
+        assert_eq!(content.to_string(), "");
+        assert_eq!(editor.cursor, Position::new(0, 0));
+
+        let redo = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL);
+        editor.handle_input(redo, &mut content, &mut history);
+        assert_eq!(content.to_string(), "hi");
+        assert_eq!(editor.cursor, Position::new(0, 2));
+    }
+
+    #[test]
+    fn test_editor_shift_selection_is_replaced_by_typing() {
+        let mut editor = Editor::new();
+        let mut content = Rope::from("hello world");
+        let mut history = History::new();
+        let select = KeyEvent::new(KeyCode::End, KeyModifiers::SHIFT);
+        editor.handle_input(select, &mut content, &mut history);
+        for _ in 0..6 {
+            let key = KeyEvent::new(KeyCode::Left, KeyModifiers::SHIFT);
+            editor.handle_input(key, &mut content, &mut history);
+        }
+        assert_eq!(editor.selection_range(&content), Some((0, 5)));
+
+        let key = KeyEvent::new(KeyCode::Char('J'), KeyModifiers::SHIFT);
+        editor.handle_input(key, &mut content, &mut history);
+        assert_eq!(content.to_string(), "J world");
+        assert_eq!(editor.selection_range(&content), None);
+
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        let select_all = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL);
+        editor.handle_input(select_all, &mut content, &mut history);
+        editor.handle_input(backspace, &mut content, &mut history);
+        assert_eq!(content.to_string(), "");
+    }
+
+    #[test]
+    fn test_editor_insert_text_moves_cursor_to_end_of_block() {
+        let mut editor = Editor::new();
+        let mut content = Rope::from("ac");
+        let mut history = History::new();
+        editor.cursor = Position::new(0, 1);
+        editor.insert_text("b\nxy", &mut content, &mut history);
+        assert_eq!(content.to_string(), "ab\nxyc");
+        assert_eq!(editor.cursor, Position::new(1, 2));
+
+        let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        editor.handle_input(undo, &mut content, &mut history);
+        assert_eq!(content.to_string(), "ac");
+    }
+
+    #[test]
+    fn test_editor_scroll_by_keeps_cursor_in_view() {
+        let mut editor = Editor::new();
+        let content = Rope::from("a\nbb\nc\nd\ne\nf\n");
+        editor.scroll_by(3, &content, 2);
+        assert_eq!(editor.scroll_offset.row, 3);
+        assert_eq!(editor.cursor, Position::new(3, 0));
+
+        editor.move_to(Position::new(1, 10), &content, false);
+        assert_eq!(editor.cursor, Position::new(1, 2));
+    }
+
+    #[test]
+    fn test_editor_indent_and_outdent() {
+        let mut editor = Editor::new();
+        let mut content = Rope::from("ab\n  cd\n\nef");
+        let mut history = History::new();
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        let back_tab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+
+        editor.cursor = Position::new(0, 1);
+        editor.handle_input(tab, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\n  cd\n\nef");
+        assert_eq!(editor.cursor, Position::new(0, 4));
+        editor.handle_input(back_tab, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\n  cd\n\nef");
+
+        // A selection over several lines indents each non-empty line as one undo step
+        editor.anchor = Some(Position::new(1, 0));
+        editor.cursor = Position::new(3, 1);
+        editor.handle_input(tab, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\n      cd\n\n    ef");
+        assert_eq!(editor.anchor, Some(Position::new(1, 0)));
+        assert_eq!(editor.cursor, Position::new(3, 5));
+        editor.handle_input(back_tab, &mut content, &mut history);
+        editor.handle_input(back_tab, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\ncd\n\nef");
+
+        let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        editor.handle_input(undo, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\n  cd\n\nef");
+
+        editor.indent.hard_tabs = true;
+        editor.anchor = None;
+        editor.cursor = Position::new(3, 0);
+        editor.handle_input(tab, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\n  cd\n\n\tef");
+        editor.handle_input(back_tab, &mut content, &mut history);
+        assert_eq!(content.to_string(), "a   b\n  cd\n\nef");
+    }
+
+    #[test]
+    fn test_editor_moves_by_grapheme_and_display_column() {
+        let mut editor = Editor::new();
+        let mut content = Rope::from("e\u{301}x\n\tb\n日本");
+        let mut history = History::new();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        editor.handle_input(key(KeyCode::Right), &mut content, &mut history);
+        assert_eq!(editor.cursor, Position::new(0, 2));
+
+        editor.cursor = Position::new(1, 1);
+        assert_eq!(editor.cursor_column(&content), 4);
+        editor.handle_input(key(KeyCode::Down), &mut content, &mut history);
+        assert_eq!(editor.cursor, Position::new(2, 2));
+        editor.handle_input(key(KeyCode::Left), &mut content, &mut history);
+        assert_eq!(editor.cursor_column(&content), 2);
+    }
+
+    #[test]
+    fn test_editor_line_ends_exclude_line_breaks() {
+        for line_break in ["\n", "\r\n", "\r"] {
+            let mut editor = Editor::new();
+            let mut content = Rope::from(format!("ab{line_break}cd"));
+            let mut history = History::new();
+            let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+            editor.handle_input(key(KeyCode::End), &mut content, &mut history);
+            assert_eq!(editor.cursor, Position::new(0, 2), "{line_break:?}");
+            editor.handle_input(key(KeyCode::Char('!')), &mut content, &mut history);
+            assert_eq!(content.to_string(), format!("ab!{line_break}cd"));
+            assert_eq!(editor.get_visible_lines(&content, 5), ["ab!", "cd"]);
+
+            editor.handle_input(key(KeyCode::Right), &mut content, &mut history);
+            assert_eq!(editor.cursor, Position::new(1, 0));
+            editor.handle_input(key(KeyCode::Left), &mut content, &mut history);
+            assert_eq!(editor.cursor, Position::new(0, 3));
+            editor.handle_input(key(KeyCode::Delete), &mut content, &mut history);
+            assert_eq!(content.to_string(), "ab!cd");
+
+            content = Rope::from(format!("ab{line_break}cd"));
+            editor.cursor = Position::new(1, 0);
+            editor.handle_input(key(KeyCode::Backspace), &mut content, &mut history);
+            assert_eq!(content.to_string(), "abcd");
+            assert_eq!(editor.cursor, Position::new(0, 2));
+        }
+    }
 }
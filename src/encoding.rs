@@ -0,0 +1,249 @@
+use anyhow::{bail, Result};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+/// Number of bytes looked at when deciding whether a file is binary.
+const BINARY_CHECK_BYTES: usize = 8 * 1024;
+
+/// Character encoding of a file on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 BOM",
+            Encoding::Utf16Le => "UTF-16 LE",
+            Encoding::Utf16Be => "UTF-16 BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// The encoding that follows this one when cycling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            Encoding::Utf8 => Encoding::Utf8Bom,
+            Encoding::Utf8Bom => Encoding::Utf16Le,
+            Encoding::Utf16Le => Encoding::Utf16Be,
+            Encoding::Utf16Be => Encoding::Latin1,
+            Encoding::Latin1 => Encoding::Utf8,
+        }
+    }
+
+    /// Detect the encoding of `bytes` from its byte order mark, falling back to UTF-8 when
+    /// the bytes are valid UTF-8 and Latin-1 otherwise, and decode them. A byte order mark
+    /// followed by bytes that are invalid in its encoding is read as Latin-1 too, so saving
+    /// keeps every byte.
+    pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+        if let Some(Ok(text)) = bytes.strip_prefix(UTF8_BOM).map(std::str::from_utf8) {
+            return (text.to_string(), Encoding::Utf8Bom);
+        }
+        if let Some(text) = bytes
+            .strip_prefix(UTF16LE_BOM)
+            .and_then(|rest| decode_utf16(rest, u16::from_le_bytes))
+        {
+            return (text, Encoding::Utf16Le);
+        }
+        if let Some(text) = bytes
+            .strip_prefix(UTF16BE_BOM)
+            .and_then(|rest| decode_utf16(rest, u16::from_be_bytes))
+        {
+            return (text, Encoding::Utf16Be);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), Encoding::Utf8),
+            // Every byte is a valid Latin-1 char, so this never fails
+            Err(_) => (bytes.iter().map(|&b| b as char).collect(), Encoding::Latin1),
+        }
+    }
+
+    /// Encode `text`, including the byte order mark where the encoding has one.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>> {
+        Ok(match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Encoding::Utf16Le => {
+                let mut bytes = UTF16LE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+                bytes
+            }
+            Encoding::Utf16Be => {
+                let mut bytes = UTF16BE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+                bytes
+            }
+            Encoding::Latin1 => {
+                if let Some(c) = text.chars().find(|&c| c as u32 > 0xFF) {
+                    bail!("'{c}' cannot be encoded as Latin-1");
+                }
+                text.chars().map(|c| c as u8).collect()
+            }
+        })
+    }
+}
+
+/// Whether `bytes` look like binary data rather than text: a NUL byte near the start of
+/// a file without a UTF-16 byte order mark.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.starts_with(UTF16LE_BOM) || bytes.starts_with(UTF16BE_BOM) {
+        return false;
+    }
+    bytes.iter().take(BINARY_CHECK_BYTES).any(|&b| b == 0)
+}
+
+/// Decode UTF-16 `bytes`, or None if they are not valid UTF-16.
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+/// Line break style of a file on disk. Buffers always use `\n` internally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+        }
+    }
+
+    /// The line ending that follows this one when cycling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            LineEnding::Lf => LineEnding::CrLf,
+            LineEnding::CrLf => LineEnding::Cr,
+            LineEnding::Cr => LineEnding::Lf,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The most common line ending in `text`, LF if it has no line breaks.
+    pub fn detect(text: &str) -> Self {
+        let (lf, crlf, cr) = count_line_endings(text);
+        if crlf > lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf && cr > crlf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// Whether `text` uses more than one kind of line break. Saving it writes them all with
+    /// the same line ending.
+    pub fn is_mixed(text: &str) -> bool {
+        let (lf, crlf, cr) = count_line_endings(text);
+        [lf, crlf, cr].iter().filter(|&&n| n > 0).count() > 1
+    }
+
+    /// Replace every line break in `text` with `\n`.
+    pub fn normalize(text: &str) -> String {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    }
+
+    /// Replace every `\n` in `text` with this line ending.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            _ => text.replace('\n', self.as_str()),
+        }
+    }
+}
+
+/// Number of LF, CRLF and lone CR line breaks in `text`.
+fn count_line_endings(text: &str) -> (usize, usize, usize) {
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                crlf += 1;
+            }
+            '\r' => cr += 1,
+            '\n' => lf += 1,
+            _ => {}
+        }
+    }
+    (lf, crlf, cr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings_round_trip() {
+        let text = "caf\u{e9}\n";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Latin1,
+        ] {
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(Encoding::decode(&bytes), (text.to_string(), encoding));
+        }
+        assert_eq!(Encoding::Latin1.encode("caf\u{e9}").unwrap(), b"caf\xe9");
+        assert!(Encoding::Latin1.encode("\u{65e5}").is_err());
+
+        // A byte order mark followed by invalid data (bad UTF-8, an odd number of UTF-16
+        // bytes, an unpaired surrogate) does not make decoding lossy
+        for bytes in [
+            &b"\xEF\xBB\xBFcaf\xe9\n"[..],
+            b"\xFF\xFEa\0b",
+            b"\xFE\xFF\xD8\x00\0a",
+        ] {
+            let (text, encoding) = Encoding::decode(bytes);
+            assert_eq!(encoding, Encoding::Latin1);
+            assert_eq!(encoding.encode(&text).unwrap(), bytes);
+        }
+
+        assert!(is_binary(b"ELF\0\x01"));
+        assert!(!is_binary(&Encoding::Utf16Le.encode("abc").unwrap()));
+    }
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a"), LineEnding::Lf);
+        assert!(LineEnding::is_mixed("a\nb\rc\n"));
+        assert!(!LineEnding::is_mixed("a\r\nb\r\n"));
+
+        let text = LineEnding::normalize("a\r\nb\rc\n");
+        assert_eq!(text, "a\nb\nc\n");
+        assert_eq!(LineEnding::CrLf.apply(&text), "a\r\nb\r\nc\r\n");
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use ignore::WalkBuilder;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct FileNode {
@@ -8,6 +10,8 @@ pub struct FileNode {
     pub is_dir: bool,
     pub children: Vec<FileNode>,
     pub expanded: bool,
+    /// Excluded by .gitignore/.ignore rules (or the .git directory itself).
+    pub ignored: bool,
 }
 
 impl FileNode {
@@ -26,14 +30,28 @@ impl FileNode {
             is_dir,
             children: Vec::new(),
             expanded: false,
+            ignored: false,
         }
     }
 
-    pub fn load_children(&mut self) -> Result<()> {
+    /// Load the directory's entries, skipping those excluded by ignore files
+    /// (nested .gitignore, .ignore and global git excludes) unless `show_ignored` is set.
+    pub fn load_children(&mut self, show_ignored: bool) -> Result<()> {
         if !self.is_dir || !self.children.is_empty() {
             return Ok(());
         }
 
+        let not_ignored: HashSet<PathBuf> = WalkBuilder::new(&self.path)
+            .max_depth(Some(1))
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.depth() == 1)
+            .map(|entry| entry.into_path())
+            .collect();
+
         let entries = std::fs::read_dir(&self.path)?;
         let mut children = Vec::new();
 
@@ -41,19 +59,15 @@ impl FileNode {
             let entry = entry?;
             let path = entry.path();
 
-            // Skip hidden files and common ignore patterns
-            if let Some(name) = path.file_name() {
-                let name_str = name.to_string_lossy();
-                if name_str.starts_with('.')
-                    || name_str == "target"
-                    || name_str == "node_modules"
-                    || name_str == ".git"
-                {
-                    continue;
-                }
+            // Everything inside an ignored directory is ignored too
+            let ignored = self.ignored || !not_ignored.contains(&path);
+            if ignored && !show_ignored {
+                continue;
             }
 
-            children.push(FileNode::new(path));
+            let mut child = FileNode::new(path);
+            child.ignored = ignored;
+            children.push(child);
         }
 
         // Sort: directories first, then files, both alphabetically
@@ -67,8 +81,61 @@ impl FileNode {
         Ok(())
     }
 
-    pub fn get_display_lines(&self, depth: usize) -> Vec<String> {
-        let mut lines = Vec::new();
+    /// Find the node shown at `index` in the flattened tree; `index` is consumed as nodes are passed.
+    fn visible_node_mut(&mut self, index: &mut usize) -> Option<&mut FileNode> {
+        if *index == 0 {
+            return Some(self);
+        }
+        *index -= 1;
+        if self.expanded {
+            for child in &mut self.children {
+                if let Some(node) = child.visible_node_mut(index) {
+                    return Some(node);
+                }
+            }
+        }
+        None
+    }
+
+    /// Collect the paths of all expanded directories below and including this node.
+    fn collect_expanded(&self, paths: &mut HashSet<PathBuf>) {
+        if self.expanded {
+            paths.insert(self.path.clone());
+            for child in &self.children {
+                child.collect_expanded(paths);
+            }
+        }
+    }
+
+    /// Reload the children of every directory in `expanded`, re-expanding them.
+    fn reload(&mut self, expanded: &HashSet<PathBuf>, show_ignored: bool) -> Result<()> {
+        self.children.clear();
+        if !expanded.contains(&self.path) {
+            self.expanded = false;
+            return Ok(());
+        }
+        self.load_children(show_ignored)?;
+        self.expanded = true;
+        for child in &mut self.children {
+            if child.is_dir {
+                child.reload(expanded, show_ignored)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the nodes shown in the tree, with their depth, in display order.
+    fn collect_visible<'a>(&'a self, depth: usize, nodes: &mut Vec<(usize, &'a FileNode)>) {
+        nodes.push((depth, self));
+        if self.expanded {
+            for child in &self.children {
+                child.collect_visible(depth + 1, nodes);
+            }
+        }
+    }
+
+    /// The line shown for this node in the tree at the given depth.
+    pub fn display_line(&self, depth: usize) -> String {
         let indent = "  ".repeat(depth);
         let prefix = if self.is_dir {
             if self.expanded {
@@ -80,15 +147,7 @@ impl FileNode {
             "📄 "
         };
 
-        lines.push(format!("{}{}{}", indent, prefix, self.name));
-
-        if self.expanded {
-            for child in &self.children {
-                lines.extend(child.get_display_lines(depth + 1));
-            }
-        }
-
-        lines
+        format!("{}{}{}", indent, prefix, self.name)
     }
 }
 
@@ -96,6 +155,10 @@ pub struct FileExplorer {
     pub root: FileNode,
     pub current_path: PathBuf,
     pub selected_index: usize,
+    /// Index of the first display line shown in the panel.
+    pub scroll_offset: usize,
+    /// Also list hidden-by-ignore-rules entries (shown dimmed).
+    pub show_ignored: bool,
 }
 
 impl FileExplorer {
@@ -107,11 +170,157 @@ impl FileExplorer {
             root,
             current_path: current_dir,
             selected_index: 0,
+            scroll_offset: 0,
+            show_ignored: false,
         })
     }
 
-    pub fn get_display_lines(&self) -> Vec<String> {
-        self.root.get_display_lines(0)
+    /// The node on line `index` of the display lines.
+    pub fn node_at_mut(&mut self, index: usize) -> Option<&mut FileNode> {
+        let mut index = index;
+        self.root.visible_node_mut(&mut index)
+    }
+
+    /// The currently selected node.
+    pub fn selected_node_mut(&mut self) -> Option<&mut FileNode> {
+        self.node_at_mut(self.selected_index)
+    }
+
+    /// The nodes shown in the tree with their depth, in display order.
+    pub fn visible_nodes(&self) -> Vec<(usize, &FileNode)> {
+        let mut nodes = Vec::new();
+        self.root.collect_visible(0, &mut nodes);
+        nodes
+    }
+
+    /// Number of lines in the flattened tree.
+    pub fn len(&self) -> usize {
+        self.visible_nodes().len()
+    }
+
+    /// Move the selection by `delta` lines, stopping at the first and last entries.
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.len().saturating_sub(1);
+        self.selected_index = self.selected_index.saturating_add_signed(delta).min(last);
+    }
+
+    /// Select the last entry.
+    pub fn select_last(&mut self) {
+        self.selected_index = self.len().saturating_sub(1);
+    }
+
+    /// Collapse the selected directory, or select the parent of the selected entry.
+    pub fn collapse_selected(&mut self) {
+        if let Some(node) = self.selected_node_mut() {
+            if node.is_dir && node.expanded {
+                node.expanded = false;
+                return;
+            }
+        }
+
+        let nodes = self.visible_nodes();
+        let Some(&(depth, _)) = nodes.get(self.selected_index) else {
+            return;
+        };
+        if let Some(parent) = (0..self.selected_index).rev().find(|&i| nodes[i].0 < depth) {
+            self.selected_index = parent;
+        }
+    }
+
+    /// Expand the selected directory, or select its first child if already expanded.
+    pub fn expand_selected(&mut self) -> Result<()> {
+        let index = self.selected_index;
+        let show_ignored = self.show_ignored;
+        let Some(node) = self.selected_node_mut() else {
+            return Ok(());
+        };
+        if !node.is_dir {
+            return Ok(());
+        }
+        if node.expanded {
+            if !node.children.is_empty() {
+                self.selected_index = index + 1;
+            }
+        } else {
+            node.load_children(show_ignored)?;
+            node.expanded = true;
+        }
+        Ok(())
+    }
+
+    /// Adjust the scroll offset so the selection is within `height` visible lines.
+    pub fn ensure_selected_visible(&mut self, height: usize) {
+        if height == 0 {
+            return;
+        }
+        let last = self.len().saturating_sub(1);
+        self.selected_index = self.selected_index.min(last);
+        if self.selected_index < self.scroll_offset {
+            self.scroll_offset = self.selected_index;
+        } else if self.selected_index >= self.scroll_offset + height {
+            self.scroll_offset = self.selected_index + 1 - height;
+        }
+    }
+
+    /// Re-read the tree from disk, keeping expanded directories and the selected path,
+    /// and additionally expanding `dir`.
+    pub fn refresh(&mut self, dir: Option<&Path>) -> Result<()> {
+        let selected = self.selected_node_mut().map(|node| node.path.clone());
+        let mut expanded = HashSet::new();
+        self.root.collect_expanded(&mut expanded);
+        expanded.extend(dir.map(Path::to_path_buf));
+        self.root = FileNode::new(self.root.path.clone());
+        self.root.reload(&expanded, self.show_ignored)?;
+        if let Some(path) = selected {
+            self.select_path(&path);
+        }
+        Ok(())
+    }
+
+    /// Paths of all expanded directories, sorted.
+    pub fn expanded_paths(&self) -> Vec<PathBuf> {
+        let mut expanded = HashSet::new();
+        self.root.collect_expanded(&mut expanded);
+        let mut paths: Vec<PathBuf> = expanded.into_iter().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Reload the tree with exactly the directories in `paths` expanded. Directories that
+    /// no longer exist are skipped.
+    pub fn restore_expanded(&mut self, paths: &[PathBuf]) -> Result<()> {
+        let expanded: HashSet<PathBuf> = paths.iter().cloned().collect();
+        self.root = FileNode::new(self.root.path.clone());
+        self.root.reload(&expanded, self.show_ignored)
+    }
+
+    /// Select the entry for `path` if it is visible, otherwise keep the selection in range.
+    pub fn select_path(&mut self, path: &Path) {
+        let nodes = self.visible_nodes();
+        match nodes.iter().position(|(_, node)| node.path == path) {
+            Some(index) => self.selected_index = index,
+            None => self.selected_index = self.selected_index.min(nodes.len().saturating_sub(1)),
+        }
+    }
+
+    /// Toggle listing of ignored entries and reload the tree.
+    pub fn toggle_show_ignored(&mut self) -> Result<()> {
+        self.show_ignored = !self.show_ignored;
+        self.refresh(None)
+    }
+
+    /// Scroll the panel by `delta` lines, dragging the selection along where it would
+    /// leave the `height` visible lines.
+    pub fn scroll_by(&mut self, delta: isize, height: usize) {
+        let max_offset = self.len().saturating_sub(height);
+        self.scroll_offset = self
+            .scroll_offset
+            .saturating_add_signed(delta)
+            .min(max_offset);
+        let last_visible = (self.scroll_offset + height).saturating_sub(1);
+        self.selected_index = self
+            .selected_index
+            .clamp(self.scroll_offset, last_visible.max(self.scroll_offset));
     }
 }
 
@@ -138,4 +347,90 @@ mod tests {
         assert!(!node.is_dir);
         fs::remove_file(file).unwrap();
     }
+
+    #[test]
+    fn test_explorer_keyboard_navigation() {
+        let dir = env::temp_dir().join(format!("tui_editor_nav_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("inner.rs"), "").unwrap();
+        fs::write(dir.join("top.rs"), "").unwrap();
+
+        let mut explorer = FileExplorer::new().unwrap();
+        explorer.root = FileNode::new(dir.clone());
+        explorer.expand_selected().unwrap();
+        assert_eq!(explorer.len(), 3);
+
+        explorer.expand_selected().unwrap();
+        assert_eq!(explorer.selected_index, 1);
+        explorer.expand_selected().unwrap();
+        explorer.move_selection(1);
+        assert_eq!(
+            explorer.selected_node_mut().unwrap().name,
+            "inner.rs".to_string()
+        );
+
+        explorer.collapse_selected();
+        assert_eq!(explorer.selected_index, 1);
+        explorer.collapse_selected();
+        assert_eq!(explorer.len(), 3);
+        explorer.select_last();
+        assert_eq!(explorer.selected_node_mut().unwrap().name, "top.rs");
+
+        // Scrolling past the selection keeps the offset when the panel is drawn again
+        explorer.selected_index = 0;
+        explorer.scroll_by(2, 1);
+        explorer.ensure_selected_visible(1);
+        assert_eq!((explorer.scroll_offset, explorer.selected_index), (2, 2));
+        explorer.scroll_by(-1, 1);
+        explorer.ensure_selected_visible(1);
+        assert_eq!((explorer.scroll_offset, explorer.selected_index), (1, 1));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_children_respects_ignore_files() {
+        let dir = env::temp_dir().join(format!("tui_editor_ignore_{}", std::process::id()));
+        fs::create_dir_all(dir.join("dist")).unwrap();
+        fs::create_dir_all(dir.join(".github")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join(".gitignore"), "dist/\n*.log\n").unwrap();
+        fs::write(dir.join(".env.example"), "").unwrap();
+        fs::write(dir.join("debug.log"), "").unwrap();
+
+        let mut explorer = FileExplorer::new().unwrap();
+        explorer.root = FileNode::new(dir.clone());
+        explorer.expand_selected().unwrap();
+        let names = |explorer: &FileExplorer| -> Vec<(String, bool)> {
+            explorer
+                .root
+                .children
+                .iter()
+                .map(|c| (c.name.clone(), c.ignored))
+                .collect()
+        };
+        assert_eq!(
+            names(&explorer),
+            [
+                (".github".to_string(), false),
+                (".env.example".to_string(), false),
+                (".gitignore".to_string(), false),
+            ]
+        );
+
+        explorer.toggle_show_ignored().unwrap();
+        assert_eq!(explorer.root.children.len(), 6);
+        assert!(explorer
+            .root
+            .children
+            .iter()
+            .any(|c| c.name == "dist" && c.ignored));
+        assert!(explorer
+            .root
+            .children
+            .iter()
+            .any(|c| c.name == ".git" && c.ignored));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,346 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Create a new empty file, failing if it already exists.
+pub fn create_file(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("Cannot create {}", path.display()))?;
+    Ok(())
+}
+
+/// Create a new directory (and any missing parents), failing if it already exists.
+pub fn create_dir(path: &Path) -> Result<()> {
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    fs::create_dir_all(path)?;
+    Ok(())
+}
+
+/// Move or rename `from` to `to`, copying when it has to cross file systems.
+pub fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        bail!("{} already exists", to.display());
+    }
+    if to.starts_with(from) {
+        bail!("Cannot move {} into itself", from.display());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_path(from, to)?;
+            delete_path(from)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Copy a file or a directory tree from `from` to `to`.
+pub fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        bail!("{} already exists", to.display());
+    }
+    if !from.is_dir() {
+        fs::copy(from, to)?;
+        return Ok(());
+    }
+    if to.starts_with(from) {
+        bail!("Cannot copy {} into itself", from.display());
+    }
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete a file or a directory tree.
+pub fn delete_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Where moving `path` to the user-entered `destination` puts it. Relative destinations are
+/// resolved against `base`, and existing directories receive the entry under its own name.
+pub fn move_destination(path: &Path, destination: &str, base: &Path) -> PathBuf {
+    let destination = base.join(destination);
+    match path.file_name() {
+        Some(name) if destination.is_dir() => destination.join(name),
+        _ => destination,
+    }
+}
+
+/// Where to keep a copy of a file's previous contents when saving over it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Backup {
+    #[default]
+    None,
+    /// Next to the file, named with a trailing `~`.
+    Suffix,
+    /// In the given directory, named after the file's full path.
+    Dir(PathBuf),
+}
+
+impl Backup {
+    /// Path of the backup copy of `path`, if backups are enabled.
+    fn path_for(&self, path: &Path) -> Option<PathBuf> {
+        match self {
+            Backup::None => None,
+            Backup::Suffix => {
+                let mut name = path.file_name()?.to_os_string();
+                name.push("~");
+                Some(path.with_file_name(name))
+            }
+            Backup::Dir(dir) => {
+                // Encode the whole path so files with the same name do not collide
+                let name = path.to_string_lossy().replace(std::path::is_separator, "%");
+                Some(dir.join(format!("{name}~")))
+            }
+        }
+    }
+}
+
+/// Save `bytes` to `path` without ever leaving a truncated file behind: the data is
+/// written and synced to a temporary file next to the target, which then replaces it.
+///
+/// Symlinks are followed so the link itself is kept, and the permissions (and on Unix the
+/// ownership) of a replaced file carry over. If the directory does not allow creating the
+/// temporary file the save fails rather than risk truncating the file. Problems that did
+/// not stop the save, such as a failed backup, are returned as warnings.
+pub fn write_atomic(path: &Path, bytes: &[u8], backup: &Backup) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    let target = resolve_symlinks(path);
+    let existing = fs::metadata(&target).ok();
+
+    if let Some(backup_path) = backup.path_for(&target).filter(|_| existing.is_some()) {
+        let result = backup_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(&target, &backup_path));
+        if let Err(e) = result {
+            warnings.push(format!("backup to {} failed: {e}", backup_path.display()));
+        }
+    }
+
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = target
+        .file_name()
+        .map_or("file".into(), |n| n.to_string_lossy());
+    let temp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+    let create = || {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+    };
+    let file = match create() {
+        // Only a crashed save of this or an earlier process with the same id leaves it behind
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            fs::remove_file(&temp).and_then(|_| create())
+        }
+        result => result,
+    };
+    let mut file = file.with_context(|| format!("Cannot create {}", temp.display()))?;
+
+    let result = (|| -> Result<()> {
+        file.write_all(bytes)?;
+        if let Some(metadata) = &existing {
+            fs::set_permissions(&temp, metadata.permissions())?;
+            if let Err(e) = copy_ownership(metadata, &temp) {
+                warnings.push(format!("could not keep the file owner: {e}"));
+            }
+        }
+        file.sync_all()?;
+        fs::rename(&temp, &target)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e.context(format!("Cannot write {}", target.display())));
+    }
+
+    if let Err(e) = sync_dir(&dir) {
+        warnings.push(format!("could not sync {}: {e}", dir.display()));
+    }
+    Ok(warnings)
+}
+
+/// The file `path` finally points to, following symlinks even if the target does not
+/// exist yet.
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // Bounded to not loop forever on symlink cycles
+    for _ in 0..40 {
+        match fs::read_link(&path) {
+            Ok(target) => {
+                path = match path.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    path
+}
+
+#[cfg(unix)]
+fn copy_ownership(metadata: &fs::Metadata, path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let current = fs::metadata(path)?;
+    if current.uid() == metadata.uid() && current.gid() == metadata.gid() {
+        return Ok(());
+    }
+    std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &fs::Metadata, _path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Make a rename in `dir` durable.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Directory for the editor's own state: `$XDG_STATE_HOME/tui_editor`, or
+/// `~/.local/state/tui_editor` if that is not set.
+pub fn state_dir() -> Result<PathBuf> {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .context("Neither XDG_STATE_HOME nor HOME is set")?;
+    Ok(state.join("tui_editor"))
+}
+
+/// `path` made absolute, with symlinks resolved where it exists.
+pub fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A file name identifying `path` in the state directory: its name followed by a hash of
+/// its absolute path.
+pub fn path_key(path: &Path) -> String {
+    let path = absolute(path);
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    format!("{name}-{:016x}", hasher.finish())
+}
+
+/// Default name offered when duplicating `path`, e.g. `main_copy.rs`.
+pub fn duplicate_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match path.extension() {
+        Some(ext) if !path.is_dir() => format!("{stem}_copy.{}", ext.to_string_lossy()),
+        _ => format!("{stem}_copy"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_file_operations_round_trip() {
+        let dir = env::temp_dir().join(format!("tui_editor_ops_{}", std::process::id()));
+        let file = dir.join("a").join("main.rs");
+        create_file(&file).unwrap();
+        assert!(create_file(&file).is_err());
+        fs::write(&file, "fn main() {}").unwrap();
+
+        assert_eq!(duplicate_name(&file), "main_copy.rs");
+        let copy = dir.join("a").join(duplicate_name(&file));
+        copy_path(&file, &copy).unwrap();
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "fn main() {}");
+
+        create_dir(&dir.join("b")).unwrap();
+        let moved = move_destination(&dir.join("a"), "b", &dir);
+        assert_eq!(moved, dir.join("b").join("a"));
+        move_path(&dir.join("a"), &moved).unwrap();
+        assert!(moved.join("main_copy.rs").exists());
+        assert!(move_path(&dir.join("b"), &moved.join("inner")).is_err());
+
+        delete_path(&moved).unwrap();
+        assert!(!moved.exists());
+        delete_path(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_symlinks_permissions_and_backups() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = env::temp_dir().join(format!("tui_editor_save_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("script.sh");
+        let link = dir.join("link.sh");
+        fs::write(&file, "old").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink("script.sh", &link).unwrap();
+
+        let warnings = write_atomic(&link, b"new", &Backup::Suffix).unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dir.join("script.sh~")).unwrap(), "old");
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+
+        let backups = dir.join("backups");
+        write_atomic(&file, b"newer", &Backup::Dir(backups.clone())).unwrap();
+        let backup = fs::read_dir(&backups).unwrap().next().unwrap().unwrap();
+        assert_eq!(fs::read_to_string(backup.path()).unwrap(), "new");
+        // script.sh, link.sh, script.sh~ and backups/, no temporary files
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+
+        // A temporary file left behind by a crash does not block saving
+        let temp = dir.join(format!(".script.sh.{}.tmp", std::process::id()));
+        fs::write(&temp, "stale").unwrap();
+        write_atomic(&file, b"newest", &Backup::None).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "newest");
+        assert!(!temp.exists());
+
+        delete_path(&dir).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ignore::WalkBuilder;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of files collected from the project.
+const MAX_FILES: usize = 50_000;
+/// Maximum number of bytes read for the preview.
+const PREVIEW_BYTES: u64 = 16 * 1024;
+
+/// Outcome of a key press in the [`Finder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinderResult {
+    Pending,
+    Open(PathBuf),
+    Cancel,
+}
+
+/// A match of the query against one of the files.
+#[derive(Debug, Clone)]
+pub struct FinderMatch {
+    /// Index into [`Finder::files`].
+    pub file: usize,
+    pub score: i64,
+    /// Char positions in the path that matched the query.
+    pub positions: Vec<usize>,
+}
+
+/// Fuzzy file finder over all files below a project root, respecting ignore rules.
+pub struct Finder {
+    pub root: PathBuf,
+    /// Paths relative to `root`.
+    pub files: Vec<String>,
+    pub query: String,
+    pub matches: Vec<FinderMatch>,
+    pub selected: usize,
+    /// Index of the first match shown in the list.
+    pub scroll_offset: usize,
+    preview: Option<(usize, Vec<String>)>,
+}
+
+impl Finder {
+    pub fn new(root: PathBuf) -> Self {
+        let mut files: Vec<String> = WalkBuilder::new(&root)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter_map(|entry| {
+                let relative = entry.path().strip_prefix(&root).ok()?;
+                Some(relative.to_string_lossy().into_owned())
+            })
+            .take(MAX_FILES)
+            .collect();
+        files.sort();
+
+        let mut finder = Self {
+            root,
+            files,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            scroll_offset: 0,
+            preview: None,
+        };
+        finder.update_matches();
+        finder
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FinderResult {
+        match key.code {
+            KeyCode::Esc => return FinderResult::Cancel,
+            KeyCode::Enter => {
+                if let Some(path) = self.selected_path() {
+                    return FinderResult::Open(path);
+                }
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1))
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1))
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            _ => {}
+        }
+        FinderResult::Pending
+    }
+
+    /// Append pasted `text` to the query, up to its first line break.
+    pub fn insert_str(&mut self, text: &str) {
+        self.query.push_str(text.lines().next().unwrap_or_default());
+        self.update_matches();
+    }
+
+    /// Absolute path of the highlighted file.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let m = self.matches.get(self.selected)?;
+        Some(self.root.join(&self.files[m.file]))
+    }
+
+    /// Adjust the scroll offset so the selection is within `height` visible rows.
+    pub fn ensure_selected_visible(&mut self, height: usize) {
+        if self.selected < self.scroll_offset {
+            self.scroll_offset = self.selected;
+        } else if height > 0 && self.selected >= self.scroll_offset + height {
+            self.scroll_offset = self.selected + 1 - height;
+        }
+    }
+
+    /// The first lines of the highlighted file, read once per selection.
+    pub fn preview(&mut self) -> &[String] {
+        let file = self.matches.get(self.selected).map(|m| m.file);
+        let Some(file) = file else {
+            return &[];
+        };
+        if self.preview.as_ref().map(|(f, _)| *f) != Some(file) {
+            let lines = read_preview(&self.root.join(&self.files[file]));
+            self.preview = Some((file, lines));
+        }
+        self.preview.as_ref().map_or(&[], |(_, lines)| lines)
+    }
+
+    fn update_matches(&mut self) {
+        self.matches = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(file, path)| {
+                let (score, positions) = fuzzy_score(&self.query, path)?;
+                Some(FinderMatch {
+                    file,
+                    score,
+                    positions,
+                })
+            })
+            .collect();
+        let files = &self.files;
+        self.matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| files[a.file].len().cmp(&files[b.file].len()))
+                .then_with(|| files[a.file].cmp(&files[b.file]))
+        });
+        self.selected = 0;
+        self.scroll_offset = 0;
+    }
+}
+
+fn read_preview(path: &Path) -> Vec<String> {
+    let mut bytes = Vec::new();
+    let read =
+        std::fs::File::open(path).and_then(|f| f.take(PREVIEW_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = read {
+        return vec![format!("(cannot read file: {e})")];
+    }
+    if bytes.contains(&0) {
+        return vec!["(binary file)".to_string()];
+    }
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(|line| line.replace('\t', "    "))
+        .collect()
+}
+
+/// Score `candidate` against `query`, matching the query chars in order (ignoring case).
+///
+/// Matches at the start of a path segment or word and consecutive matches score higher,
+/// gaps score lower, and a query that fits entirely in the file name gets a bonus.
+/// Returns the score and the matched char positions, or None if the query does not match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0i64;
+    let mut next = 0;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        let found = (next..chars.len()).find(|&i| chars[i].to_ascii_lowercase() == q)?;
+
+        let prev = found.checked_sub(1).map(|i| chars[i]);
+        score += match prev {
+            None | Some('/') | Some('\\') => 16,
+            Some('_') | Some('-') | Some('.') | Some(' ') => 8,
+            Some(p) if p.is_lowercase() && chars[found].is_uppercase() => 8,
+            _ => 0,
+        };
+        if found == next && !positions.is_empty() {
+            score += 10;
+        }
+        score -= (found - next) as i64;
+        score += 1;
+
+        positions.push(found);
+        next = found + 1;
+    }
+
+    let file_name_start = chars
+        .iter()
+        .rposition(|&c| c == '/' || c == '\\')
+        .map_or(0, |i| i + 1);
+    if positions.first().is_some_and(|&p| p >= file_name_start) {
+        score += 20;
+    }
+
+    Some((score, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_prefers_segment_starts_and_file_names() {
+        assert!(fuzzy_score("xyz", "src/main.rs").is_none());
+        let (_, positions) = fuzzy_score("mrs", "src/main.rs").unwrap();
+        assert_eq!(positions, vec![4, 9, 10]);
+
+        let (file_name, _) = fuzzy_score("app", "src/app.rs").unwrap();
+        let (scattered, _) = fuzzy_score("app", "assets/paper/plan.txt").unwrap();
+        assert!(file_name > scattered);
+
+        let (boundary, _) = fuzzy_score("fe", "src/file_explorer.rs").unwrap();
+        let (inner, _) = fuzzy_score("fe", "src/safe.rs").unwrap();
+        assert!(boundary > inner);
+    }
+
+    #[test]
+    fn test_finder_lists_and_filters_project_files() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_finder_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(dir.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.join("target").join("out.rs"), "").unwrap();
+
+        let mut finder = Finder::new(dir.clone());
+        assert_eq!(finder.files.len(), 2);
+        for c in "main".chars() {
+            finder.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        assert_eq!(finder.preview(), ["fn main() {}".to_string()]);
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(
+            finder.handle_key(enter),
+            FinderResult::Open(dir.join("src").join("main.rs"))
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use ratatui::style::{Color, Style};
 use ratatui::text::Span;
+use std::path::Path;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Style as SyntectStyle, ThemeSet};
 use syntect::parsing::{SyntaxReference, SyntaxSet};
@@ -26,16 +27,41 @@ impl Highlighter {
         self.syntax_set.find_syntax_by_extension(extension)
     }
 
-    /// Highlight a line of code for a given file extension, returning ratatui Spans.
-    pub fn highlight_line(&self, line: &str, extension: &str) -> Vec<Span<'static>> {
+    /// Name of the syntax to highlight a file with, chosen by its extension or,
+    /// for files like `Makefile`, by its name. Plain text if nothing matches.
+    pub fn syntax_name_for(&self, path: Option<&Path>) -> String {
+        path.and_then(|path| {
+            let extension = path.extension().and_then(|e| e.to_str());
+            let file_name = path.file_name().and_then(|n| n.to_str());
+            extension
+                .and_then(|e| self.get_syntax(e))
+                .or_else(|| file_name.and_then(|n| self.get_syntax(n)))
+        })
+        .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+        .name
+        .clone()
+    }
+
+    /// Highlight a line of code (without its line break) with the named syntax,
+    /// returning ratatui Spans.
+    pub fn highlight_line(&self, line: &str, syntax_name: &str) -> Vec<Span<'static>> {
         let syntax = self
-            .get_syntax(extension)
+            .syntax_set
+            .find_syntax_by_name(syntax_name)
             .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
         let mut h = HighlightLines::new(syntax, &self.theme);
-        let regions = h.highlight_line(line, &self.syntax_set).unwrap_or_default();
+        // The syntax definitions expect every line to end with a newline
+        let line = format!("{line}\n");
+        let regions = h
+            .highlight_line(&line, &self.syntax_set)
+            .unwrap_or_default();
         regions
             .into_iter()
-            .map(|(style, text)| Span::styled(text.to_string(), syntect_style_to_tui(style)))
+            .filter_map(|(style, text)| {
+                let text = text.strip_suffix('\n').unwrap_or(text);
+                (!text.is_empty())
+                    .then(|| Span::styled(text.to_string(), syntect_style_to_tui(style)))
+            })
             .collect()
     }
 }
@@ -0,0 +1,253 @@
+use crate::editor::Position;
+use ropey::Rope;
+
+/// A single reversible change: `removed` was replaced by `inserted` at `char_idx`.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub char_idx: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Edit {
+    fn apply(&self, content: &mut Rope) {
+        let end = self.char_idx + self.removed.chars().count();
+        content.remove(self.char_idx..end);
+        content.insert(self.char_idx, &self.inserted);
+    }
+
+    fn revert(&self, content: &mut Rope) {
+        let end = self.char_idx + self.inserted.chars().count();
+        content.remove(self.char_idx..end);
+        content.insert(self.char_idx, &self.removed);
+    }
+}
+
+/// What kind of editing produced an edit, used to decide which edits are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    /// Typing a character.
+    Insert,
+    /// Backspace.
+    DeleteBackward,
+    /// Delete.
+    DeleteForward,
+    /// Anything that always forms its own undo step (newlines, pastes, ...).
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Transaction {
+    id: u64,
+    kind: EditKind,
+    edits: Vec<Edit>,
+    cursor_before: Position,
+    cursor_after: Position,
+}
+
+impl Transaction {
+    /// Whether `edit` continues the run of edits in this transaction.
+    fn continues(&self, kind: EditKind, edit: &Edit) -> bool {
+        let Some(last) = self.edits.last() else {
+            return false;
+        };
+        if kind != self.kind {
+            return false;
+        }
+        match kind {
+            EditKind::Insert => {
+                edit.removed.is_empty()
+                    && edit.char_idx == last.char_idx + last.inserted.chars().count()
+            }
+            EditKind::DeleteBackward => {
+                edit.inserted.is_empty()
+                    && edit.char_idx + edit.removed.chars().count() == last.char_idx
+            }
+            EditKind::DeleteForward => edit.inserted.is_empty() && edit.char_idx == last.char_idx,
+            EditKind::Other => false,
+        }
+    }
+}
+
+/// Undo/redo history for a single buffer.
+///
+/// Every state of the buffer is identified by the id of the transaction on top of the
+/// undo stack (0 for the initial state), which lets the history tell whether the buffer
+/// is back at the state it was last saved in.
+#[derive(Debug, Clone)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    next_id: u64,
+    saved_state: Option<u64>,
+    group_open: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            next_id: 1,
+            saved_state: Some(0),
+            group_open: false,
+        }
+    }
+
+    /// Record an edit that has already been applied to the buffer.
+    pub fn record(
+        &mut self,
+        edit: Edit,
+        kind: EditKind,
+        cursor_before: Position,
+        cursor_after: Position,
+    ) {
+        if !self.redo_stack.is_empty() {
+            if self
+                .redo_stack
+                .iter()
+                .any(|t| Some(t.id) == self.saved_state)
+            {
+                self.saved_state = None;
+            }
+            self.redo_stack.clear();
+        }
+
+        let current = self.current_state();
+        if self.group_open && self.saved_state != Some(current) {
+            if let Some(top) = self.undo_stack.last_mut() {
+                if top.continues(kind, &edit) {
+                    top.edits.push(edit);
+                    top.cursor_after = cursor_after;
+                    return;
+                }
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.undo_stack.push(Transaction {
+            id,
+            kind,
+            edits: vec![edit],
+            cursor_before,
+            cursor_after,
+        });
+        self.group_open = kind != EditKind::Other;
+    }
+
+    /// Stop merging further edits into the most recent undo step.
+    pub fn break_group(&mut self) {
+        self.group_open = false;
+    }
+
+    /// Undo the most recent step, returning the cursor position to restore.
+    pub fn undo(&mut self, content: &mut Rope) -> Option<Position> {
+        let transaction = self.undo_stack.pop()?;
+        for edit in transaction.edits.iter().rev() {
+            edit.revert(content);
+        }
+        let cursor = transaction.cursor_before;
+        self.redo_stack.push(transaction);
+        self.group_open = false;
+        Some(cursor)
+    }
+
+    /// Redo the most recently undone step, returning the cursor position to restore.
+    pub fn redo(&mut self, content: &mut Rope) -> Option<Position> {
+        let transaction = self.redo_stack.pop()?;
+        for edit in &transaction.edits {
+            edit.apply(content);
+        }
+        let cursor = transaction.cursor_after;
+        self.undo_stack.push(transaction);
+        self.group_open = false;
+        Some(cursor)
+    }
+
+    /// Remember the current state as the one that matches the file on disk.
+    pub fn mark_saved(&mut self) {
+        self.saved_state = Some(self.current_state());
+        self.group_open = false;
+    }
+
+    /// Forget the saved state, e.g. because the file changed on disk.
+    pub fn mark_unsaved(&mut self) {
+        self.saved_state = None;
+    }
+
+    /// Whether the buffer differs from the state it was last saved in.
+    pub fn is_modified(&self) -> bool {
+        self.saved_state != Some(self.current_state())
+    }
+
+    fn current_state(&self) -> u64 {
+        self.undo_stack.last().map_or(0, |t| t.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(content: &mut Rope, history: &mut History, idx: usize, text: &str) {
+        content.insert(idx, text);
+        let edit = Edit {
+            char_idx: idx,
+            removed: String::new(),
+            inserted: text.to_string(),
+        };
+        let after = Position::new(0, idx + text.chars().count());
+        history.record(edit, EditKind::Insert, Position::new(0, idx), after);
+    }
+
+    #[test]
+    fn test_history_groups_consecutive_typing() {
+        let mut content = Rope::new();
+        let mut history = History::new();
+        for (i, c) in "abc".chars().enumerate() {
+            insert(&mut content, &mut history, i, &c.to_string());
+        }
+        assert!(history.is_modified());
+
+        let cursor = history.undo(&mut content).unwrap();
+        assert_eq!(content.to_string(), "");
+        assert_eq!(cursor, Position::new(0, 0));
+        assert!(!history.is_modified());
+
+        let cursor = history.redo(&mut content).unwrap();
+        assert_eq!(content.to_string(), "abc");
+        assert_eq!(cursor, Position::new(0, 3));
+    }
+
+    #[test]
+    fn test_history_undo_to_saved_state_clears_modified() {
+        let mut content = Rope::new();
+        let mut history = History::new();
+        insert(&mut content, &mut history, 0, "a");
+        history.mark_saved();
+        insert(&mut content, &mut history, 1, "b");
+        assert!(history.is_modified());
+
+        history.undo(&mut content);
+        assert_eq!(content.to_string(), "a");
+        assert!(!history.is_modified());
+
+        history.undo(&mut content);
+        assert!(history.is_modified());
+    }
+
+    #[test]
+    fn test_history_new_edit_after_undo_drops_saved_state() {
+        let mut content = Rope::new();
+        let mut history = History::new();
+        insert(&mut content, &mut history, 0, "a");
+        history.mark_saved();
+        history.undo(&mut content);
+        history.break_group();
+        insert(&mut content, &mut history, 0, "b");
+        assert!(history.is_modified());
+        history.undo(&mut content);
+        assert!(history.is_modified());
+    }
+}
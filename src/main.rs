@@ -1,22 +1,49 @@
 mod app;
+mod clipboard;
 mod editor;
+mod encoding;
 mod file_explorer;
+mod file_ops;
+mod finder;
 mod highlight;
+mod history;
+mod merge;
+mod pane;
+mod prompt;
+mod search;
+mod session;
+mod swap;
+mod text;
 mod ui;
+mod watcher;
 
 use anyhow::Result;
 use app::App;
 use clap::Parser;
+use clipboard::{Clipboard, ClipboardProvider};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use editor::Indent;
+use file_ops::Backup;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
+    layout::Direction,
     Terminal,
 };
+use session::SessionStore;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use swap::SwapStore;
+
+/// How long to wait for input before checking open files for changes on disk.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
@@ -28,31 +55,104 @@ struct Cli {
     /// Directory to open
     #[arg(short = 'd', long = "dir")]
     dir: Option<String>,
+
+    /// Clipboard provider: auto, internal, osc52 or command
+    #[arg(long = "clipboard", default_value = "auto")]
+    clipboard: String,
+
+    /// Command that receives copied text on stdin (e.g. "wl-copy")
+    #[arg(long = "copy-command")]
+    copy_command: Option<String>,
+
+    /// Command that prints the clipboard contents (e.g. "wl-paste --no-newline")
+    #[arg(long = "paste-command")]
+    paste_command: Option<String>,
+
+    /// Number of columns per indentation level
+    #[arg(long = "tab-width", default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..=16))]
+    tab_width: u16,
+
+    /// Indent with tab characters instead of spaces
+    #[arg(long = "hard-tabs")]
+    hard_tabs: bool,
+
+    /// Keep the previous version of saved files as `file~`
+    #[arg(long = "backup")]
+    backup: bool,
+
+    /// Keep the previous version of saved files in this directory
+    #[arg(long = "backup-dir", conflicts_with = "backup")]
+    backup_dir: Option<PathBuf>,
+
+    /// Neither restore nor save the open tabs of this directory
+    #[arg(long = "no-session")]
+    no_session: bool,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let clipboard_provider = ClipboardProvider::from_options(
+        &cli.clipboard,
+        cli.copy_command.as_deref(),
+        cli.paste_command.as_deref(),
+    )?;
 
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app and run it
     let mut app = App::new()?;
+    app.clipboard = Clipboard::new(clipboard_provider);
+    app.set_indent(Indent {
+        hard_tabs: cli.hard_tabs,
+        width: cli.tab_width as usize,
+    });
+    app.backup = match cli.backup_dir {
+        Some(dir) => Backup::Dir(dir),
+        None if cli.backup => Backup::Suffix,
+        None => Backup::None,
+    };
 
     // If a directory is specified, set it as the root for the file explorer
     if let Some(dir) = cli.dir {
         app.set_directory(dir)?;
     }
 
-    // If a file is specified, open it in a new tab
-    if let Some(file) = cli.file {
-        app.open_file(file)?;
+    // A directory given as the file also becomes the explorer root
+    let file = match cli.file {
+        Some(file) if std::path::Path::new(&file).is_dir() => {
+            app.set_directory(file)?;
+            None
+        }
+        file => file,
+    };
+
+    // Reopen the tabs left open in this directory last time
+    if !cli.no_session {
+        app.sessions = SessionStore::open_default().ok();
+        app.restore_saved_session();
     }
 
+    // If a file is specified, open it in a new tab or switch to it if already restored
+    if let Some(file) = file {
+        if let Err(e) = app.open_file(file) {
+            app.set_status_message(format!("Error opening file: {e}"));
+        }
+    }
+
+    // Keep swap files of modified tabs for crash recovery and offer to recover old ones
+    app.swap = SwapStore::open_default().ok();
+    app.find_leftover_swap_files();
+
     let res = run_app(&mut terminal, app);
 
     // Restore terminal
@@ -60,7 +160,8 @@ fn main() -> Result<()> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -80,44 +181,136 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
             }
         })?;
 
-        if let Event::Key(key) = event::read()? {
-            if app.show_help {
-                app.show_help = false;
-                continue;
+        if event::poll(POLL_INTERVAL)? {
+            match event::read()? {
+                Event::Key(key) => handle_key(&mut app, key),
+                Event::Paste(text) => app.paste_text(&text),
+                // Clicks must not switch tabs under an open dialog
+                Event::Mouse(mouse) if !app.modal_open() => app.handle_mouse(mouse),
+                _ => {}
             }
+        }
+        app.tick();
+
+        if app.should_quit {
+            app.remove_swap_files();
+            return app.save_session();
+        }
+    }
+}
+
+/// Handle a key press, giving open popups priority over global shortcuts.
+fn handle_key(app: &mut App, key: KeyEvent) {
+    if app.show_help {
+        app.show_help = false;
+        return;
+    }
 
-            match key.code {
-                KeyCode::Char('q') => {
-                    return Ok(());
-                }
-                KeyCode::Char('n') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        app.new_file();
-                    }
-                }
-                KeyCode::Char('o') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        // TODO: Implement open file dialog
-                        app.set_status_message("Open file dialog not implemented".to_string());
-                    }
-                }
-                KeyCode::Char('s') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        if let Err(e) = app.save_current_file() {
-                            app.set_status_message(format!("Error saving file: {e}"));
-                        }
-                    }
-                }
-                KeyCode::Tab => {
-                    app.toggle_panel();
-                }
-                KeyCode::F(1) => {
-                    app.show_help = !app.show_help;
-                }
-                _ => {
-                    app.handle_input(key);
-                }
+    if app.finder.is_some() {
+        app.handle_finder_input(key);
+        return;
+    }
+
+    if app.prompt.is_some() || app.confirm.is_some() {
+        app.handle_modal_input(key);
+        return;
+    }
+
+    if app.search.is_some() {
+        app.handle_search_input(key);
+        return;
+    }
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    match key.code {
+        KeyCode::Char('q') if ctrl => {
+            app.request_quit();
+        }
+        KeyCode::Char('n') if ctrl => {
+            app.new_file();
+        }
+        KeyCode::Char('o') if ctrl => {
+            app.open_finder();
+        }
+        KeyCode::Char('f') if ctrl => {
+            app.open_search();
+        }
+        KeyCode::Char('w') if ctrl => {
+            app.close_tab(app.current_tab);
+        }
+        KeyCode::Char('w') if alt => {
+            app.close_other_tabs();
+        }
+        KeyCode::Char('t') if alt => {
+            app.reopen_closed_tab();
+        }
+        KeyCode::PageUp if ctrl && shift => {
+            app.move_tab(-1);
+        }
+        KeyCode::PageDown if ctrl && shift => {
+            app.move_tab(1);
+        }
+        KeyCode::PageUp if ctrl => {
+            app.cycle_tab(-1);
+        }
+        KeyCode::PageDown if ctrl => {
+            app.cycle_tab(1);
+        }
+        KeyCode::Char('\\') if alt => {
+            app.split_pane(Direction::Horizontal);
+        }
+        KeyCode::Char('-') if alt => {
+            app.split_pane(Direction::Vertical);
+        }
+        KeyCode::Char('q') if alt => {
+            app.close_pane();
+        }
+        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down if alt => {
+            let direction = match key.code {
+                KeyCode::Left | KeyCode::Right => Direction::Horizontal,
+                _ => Direction::Vertical,
+            };
+            let forward = matches!(key.code, KeyCode::Right | KeyCode::Down);
+            if shift {
+                app.resize_pane(direction, if forward { 5 } else { -5 });
+            } else {
+                app.focus_pane_towards(direction, forward);
+            }
+        }
+        // Alt+1..8 select a tab by position and Alt+9 the last one
+        KeyCode::Char('9') if alt => {
+            app.select_tab(app.tabs.len().saturating_sub(1));
+        }
+        KeyCode::Char(c @ '1'..='8') if alt => {
+            app.select_tab(c as usize - '1' as usize);
+        }
+        KeyCode::Char('e') if alt => {
+            app.cycle_encoding();
+        }
+        KeyCode::Char('l') if alt => {
+            app.cycle_line_ending();
+        }
+        KeyCode::Char('s') if alt => {
+            app.start_save_as();
+        }
+        KeyCode::Char('s') if ctrl => {
+            if let Err(e) = app.save_current_file() {
+                app.set_status_message(format!("Error saving file: {e}"));
             }
         }
+        KeyCode::Char('e') if ctrl => {
+            app.toggle_focus();
+        }
+        KeyCode::Char('b') if ctrl => {
+            app.toggle_panel();
+        }
+        KeyCode::F(1) => {
+            app.show_help = !app.show_help;
+        }
+        _ => {
+            app.handle_input(key);
+        }
     }
 }
@@ -0,0 +1,166 @@
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use std::ops::Range;
+
+/// Marker lines written around the two sides of a conflict.
+const CONFLICT_START: &str = "<<<<<<< editor\n";
+const CONFLICT_BASE: &str = "||||||| original\n";
+const CONFLICT_SEPARATOR: &str = "=======\n";
+const CONFLICT_END: &str = ">>>>>>> disk\n";
+
+/// Result of a three-way merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+    pub text: String,
+    /// Number of regions changed differently on both sides, marked with conflict markers.
+    pub conflicts: usize,
+}
+
+/// A change replacing the base lines `base` with the lines `new` of the other side.
+#[derive(Debug, Clone)]
+struct Hunk {
+    base: Range<usize>,
+    new: Range<usize>,
+}
+
+/// Merge the changes from `base` to `ours` and from `base` to `theirs` line by line.
+/// Regions changed differently on both sides are kept as conflicts, showing our lines,
+/// the original lines and their lines between conflict markers.
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> Merge {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let our_hunks = hunks(&base, &ours);
+    let their_hunks = hunks(&base, &theirs);
+
+    let mut text = String::new();
+    let mut conflicts = 0;
+    let mut pos = 0;
+    let (mut i, mut j) = (0, 0);
+    loop {
+        let start = match (our_hunks.get(i), their_hunks.get(j)) {
+            (None, None) => break,
+            (Some(a), None) => a.base.start,
+            (None, Some(b)) => b.base.start,
+            (Some(a), Some(b)) => a.base.start.min(b.base.start),
+        };
+
+        // Collect every hunk from either side that overlaps or touches the region
+        let (first_ours, first_theirs) = (i, j);
+        let mut end = start;
+        loop {
+            if let Some(a) = our_hunks.get(i).filter(|a| a.base.start <= end) {
+                end = end.max(a.base.end);
+                i += 1;
+            } else if let Some(b) = their_hunks.get(j).filter(|b| b.base.start <= end) {
+                end = end.max(b.base.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        push_lines(&mut text, &base[pos..start]);
+        let region = start..end;
+        let mine = apply(&base, region.clone(), &our_hunks[first_ours..i], &ours);
+        let disk = apply(
+            &base,
+            region.clone(),
+            &their_hunks[first_theirs..j],
+            &theirs,
+        );
+        if first_theirs == j || mine == disk {
+            text.push_str(&mine);
+        } else if first_ours == i {
+            text.push_str(&disk);
+        } else {
+            conflicts += 1;
+            text.push_str(CONFLICT_START);
+            push_side(&mut text, &mine);
+            text.push_str(CONFLICT_BASE);
+            push_side(&mut text, &base[region].concat());
+            text.push_str(CONFLICT_SEPARATOR);
+            push_side(&mut text, &disk);
+            text.push_str(CONFLICT_END);
+        }
+        pos = end;
+    }
+    push_lines(&mut text, &base[pos..]);
+
+    Merge { text, conflicts }
+}
+
+/// The changes that turn `base` into `other`, with adjacent changes joined.
+fn hunks(base: &[&str], other: &[&str]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        let (tag, base_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        match hunks.last_mut() {
+            Some(last) if last.base.end == base_range.start && last.new.end == new_range.start => {
+                last.base.end = base_range.end;
+                last.new.end = new_range.end;
+            }
+            _ => hunks.push(Hunk {
+                base: base_range,
+                new: new_range,
+            }),
+        }
+    }
+    hunks
+}
+
+/// The base lines of `region` with `hunks` (all inside the region) applied.
+fn apply(base: &[&str], region: Range<usize>, hunks: &[Hunk], other: &[&str]) -> String {
+    let mut text = String::new();
+    let mut pos = region.start;
+    for hunk in hunks {
+        push_lines(&mut text, &base[pos..hunk.base.start]);
+        push_lines(&mut text, &other[hunk.new.clone()]);
+        pos = hunk.base.end;
+    }
+    push_lines(&mut text, &base[pos..region.end]);
+    text
+}
+
+fn push_lines(text: &mut String, lines: &[&str]) {
+    for line in lines {
+        text.push_str(line);
+    }
+}
+
+/// Append one side of a conflict, making sure the following marker starts a new line.
+fn push_side(text: &mut String, side: &str) {
+    text.push_str(side);
+    if !side.is_empty() && !side.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge3_combines_separate_changes() {
+        let base = "a\nb\nc\nd\n";
+        let merge = merge3(base, "A\nb\nc\nd\n", "a\nb\nc\nD\ne\n");
+        assert_eq!(merge.text, "A\nb\nc\nD\ne\n");
+        assert_eq!(merge.conflicts, 0);
+
+        let merge = merge3(base, "a\nB\nc\nd\n", "a\nB\nc\nd\n");
+        assert_eq!(merge.text, "a\nB\nc\nd\n");
+        assert_eq!(merge.conflicts, 0);
+    }
+
+    #[test]
+    fn test_merge3_marks_conflicts() {
+        let merge = merge3("a\nb\nc\n", "a\nmine\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(
+            merge.text,
+            "a\n<<<<<<< editor\nmine\n||||||| original\nb\n=======\ntheirs\n>>>>>>> disk\nc\n"
+        );
+        assert_eq!(merge.conflicts, 1);
+    }
+}
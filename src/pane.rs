@@ -0,0 +1,294 @@
+use crate::editor::Editor;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use std::collections::BTreeMap;
+
+/// Smallest and largest share of a split, in percent, that the first half can be resized to.
+const MIN_PERCENT: u16 = 10;
+const MAX_PERCENT: u16 = 90;
+
+/// A pane other than the focused one: the tab it shows and its own view onto it.
+#[derive(Debug, Clone)]
+pub struct Pane {
+    pub tab: usize,
+    pub editor: Editor,
+}
+
+/// Layout tree of the editor area: a single pane, or two halves side by side
+/// (`Direction::Horizontal`) or stacked (`Direction::Vertical`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Pane(usize),
+    Split {
+        direction: Direction,
+        /// Share of the first half, in percent.
+        percent: u16,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn contains(&self, id: usize) -> bool {
+        match self {
+            Node::Pane(pane) => *pane == id,
+            Node::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    /// Collect the pane ids in display order.
+    fn collect_ids(&self, ids: &mut Vec<usize>) {
+        match self {
+            Node::Pane(id) => ids.push(*id),
+            Node::Split { first, second, .. } => {
+                first.collect_ids(ids);
+                second.collect_ids(ids);
+            }
+        }
+    }
+
+    fn collect_areas(&self, area: Rect, areas: &mut Vec<(usize, Rect)>) {
+        match self {
+            Node::Pane(id) => areas.push((*id, area)),
+            Node::Split {
+                direction,
+                percent,
+                first,
+                second,
+            } => {
+                let halves = Layout::default()
+                    .direction(*direction)
+                    .constraints([Constraint::Percentage(*percent), Constraint::Min(0)])
+                    .split(area);
+                first.collect_areas(halves[0], areas);
+                second.collect_areas(halves[1], areas);
+            }
+        }
+    }
+
+    /// Replace pane `id` with a split of it and pane `new`.
+    fn split(&mut self, id: usize, new: usize, direction: Direction) {
+        match self {
+            Node::Pane(pane) if *pane == id => {
+                *self = Node::Split {
+                    direction,
+                    percent: 50,
+                    first: Box::new(Node::Pane(id)),
+                    second: Box::new(Node::Pane(new)),
+                };
+            }
+            Node::Pane(_) => {}
+            Node::Split { first, second, .. } => {
+                first.split(id, new, direction);
+                second.split(id, new, direction);
+            }
+        }
+    }
+
+    /// Remove pane `id`, letting the other half of its split take its place.
+    fn remove(&mut self, id: usize) {
+        let Node::Split { first, second, .. } = self else {
+            return;
+        };
+        if **first == Node::Pane(id) {
+            *self = std::mem::replace(second.as_mut(), Node::Pane(id));
+        } else if **second == Node::Pane(id) {
+            *self = std::mem::replace(first.as_mut(), Node::Pane(id));
+        } else {
+            first.remove(id);
+            second.remove(id);
+        }
+    }
+
+    /// Move the divider of the innermost `direction` split around pane `id` by `delta`
+    /// percent. Returns whether there was such a split.
+    fn resize(&mut self, id: usize, direction: Direction, delta: i16) -> bool {
+        let Node::Split {
+            direction: split_direction,
+            percent,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        if first.resize(id, direction, delta) || second.resize(id, direction, delta) {
+            return true;
+        }
+        if *split_direction != direction || !(first.contains(id) || second.contains(id)) {
+            return false;
+        }
+        let resized = (*percent as i16 + delta).clamp(MIN_PERCENT as i16, MAX_PERCENT as i16);
+        *percent = resized as u16;
+        true
+    }
+}
+
+/// The editor panes and how they are laid out. The focused pane shows the current tab
+/// through that tab's own editor; every other pane keeps its own view in a [`Pane`].
+#[derive(Debug, Clone)]
+pub struct Panes {
+    layout: Node,
+    others: BTreeMap<usize, Pane>,
+    focused: usize,
+    next_id: usize,
+}
+
+impl Panes {
+    pub fn new() -> Self {
+        Self {
+            layout: Node::Pane(0),
+            others: BTreeMap::new(),
+            focused: 0,
+            next_id: 1,
+        }
+    }
+
+    /// Id of the focused pane.
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    pub fn len(&self) -> usize {
+        self.others.len() + 1
+    }
+
+    /// Pane `id`, unless it is the focused one.
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Pane> {
+        self.others.get_mut(&id)
+    }
+
+    /// All panes except the focused one.
+    pub fn others(&self) -> impl Iterator<Item = &Pane> {
+        self.others.values()
+    }
+
+    /// All panes except the focused one.
+    pub fn others_mut(&mut self) -> impl Iterator<Item = &mut Pane> {
+        self.others.values_mut()
+    }
+
+    /// Pane ids in display order, left to right and top to bottom.
+    pub fn ids(&self) -> Vec<usize> {
+        let mut ids = Vec::new();
+        self.layout.collect_ids(&mut ids);
+        ids
+    }
+
+    /// The screen area of every pane within `area`.
+    pub fn areas(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut areas = Vec::new();
+        self.layout.collect_areas(area, &mut areas);
+        areas
+    }
+
+    /// Split the focused pane in two and focus the new half, which starts out showing the
+    /// same view. `current` is the focused pane's tab and view.
+    pub fn split(&mut self, direction: Direction, current: Pane) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.layout.split(self.focused, id, direction);
+        self.others.insert(self.focused, current);
+        self.focused = id;
+    }
+
+    /// Focus pane `id`, keeping `current` as the view of the previously focused pane.
+    /// Returns the tab and view of the newly focused pane.
+    pub fn focus(&mut self, id: usize, current: Pane) -> Option<Pane> {
+        let pane = self.others.remove(&id)?;
+        self.others.insert(self.focused, current);
+        self.focused = id;
+        Some(pane)
+    }
+
+    /// Close the focused pane and focus the pane that took its place. Returns the tab and
+    /// view of the newly focused pane, or None if this is the only pane.
+    pub fn close_focused(&mut self) -> Option<Pane> {
+        let ids = self.ids();
+        let index = ids.iter().position(|&id| id == self.focused)?;
+        let next = *ids
+            .get(index + 1)
+            .or(index.checked_sub(1).map(|i| &ids[i]))?;
+        self.layout.remove(self.focused);
+        self.focused = next;
+        self.others.remove(&next)
+    }
+
+    /// Move the divider of the innermost `direction` split around the focused pane by
+    /// `delta` percent. Returns whether there was such a split.
+    pub fn resize(&mut self, direction: Direction, delta: i16) -> bool {
+        self.layout.resize(self.focused, direction, delta)
+    }
+}
+
+/// The pane in `areas` next to pane `from` in `direction`, forwards (right or down) or
+/// backwards, preferring the one closest to the middle of `from`.
+pub fn neighbor(
+    areas: &[(usize, Rect)],
+    from: usize,
+    direction: Direction,
+    forward: bool,
+) -> Option<usize> {
+    let &(_, area) = areas.iter().find(|(id, _)| *id == from)?;
+    let (middle_x, middle_y) = (area.x + area.width / 2, area.y + area.height / 2);
+    areas
+        .iter()
+        .filter(|&&(id, other)| {
+            id != from
+                && match (direction, forward) {
+                    (Direction::Horizontal, true) => other.x >= area.right(),
+                    (Direction::Horizontal, false) => other.right() <= area.x,
+                    (Direction::Vertical, true) => other.y >= area.bottom(),
+                    (Direction::Vertical, false) => other.bottom() <= area.y,
+                }
+        })
+        .min_by_key(|(_, other)| {
+            let dx = (other.x + other.width / 2).abs_diff(middle_x);
+            let dy = (other.y + other.height / 2).abs_diff(middle_y);
+            match direction {
+                Direction::Horizontal => (dx, dy),
+                Direction::Vertical => (dy, dx),
+            }
+        })
+        .map(|&(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(tab: usize) -> Pane {
+        Pane {
+            tab,
+            editor: Editor::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_focus_resize_and_close() {
+        let mut panes = Panes::new();
+        panes.split(Direction::Horizontal, pane(0));
+        panes.split(Direction::Vertical, pane(1));
+        assert_eq!(panes.ids(), [0, 1, 2]);
+        assert_eq!(panes.focused(), 2);
+
+        let areas = panes.areas(Rect::new(0, 0, 100, 40));
+        assert_eq!(areas[0].1, Rect::new(0, 0, 50, 40));
+        assert_eq!(areas[1].1, Rect::new(50, 0, 50, 20));
+        assert_eq!(areas[2].1, Rect::new(50, 20, 50, 20));
+        assert_eq!(neighbor(&areas, 2, Direction::Horizontal, false), Some(0));
+        assert_eq!(neighbor(&areas, 2, Direction::Vertical, false), Some(1));
+        assert_eq!(neighbor(&areas, 2, Direction::Vertical, true), None);
+
+        assert!(panes.resize(Direction::Horizontal, 20));
+        assert_eq!(panes.areas(Rect::new(0, 0, 100, 40))[0].1.width, 70);
+
+        assert_eq!(panes.focus(0, pane(2)).unwrap().tab, 0);
+        assert_eq!(panes.get_mut(2).unwrap().tab, 2);
+        assert_eq!(panes.close_focused().unwrap().tab, 1);
+        assert_eq!(panes.ids(), [1, 2]);
+        assert_eq!(panes.focused(), 1);
+        assert_eq!(panes.close_focused().unwrap().tab, 2);
+        assert_eq!(panes.close_focused().map(|p| p.tab), None);
+        assert_eq!(panes.len(), 1);
+    }
+}
@@ -0,0 +1,258 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::{Path, PathBuf};
+
+/// What to do with the text entered in a [`Prompt`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptAction {
+    NewFile { dir: PathBuf },
+    NewDirectory { dir: PathBuf },
+    Rename { path: PathBuf },
+    Duplicate { path: PathBuf },
+    Move { path: PathBuf },
+    SaveAs,
+}
+
+/// Outcome of a key press in a [`Prompt`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptResult {
+    Pending,
+    Submit(String),
+    Cancel,
+}
+
+/// A single-line text input shown in a popup.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub title: String,
+    pub input: String,
+    /// Cursor position in chars.
+    pub cursor: usize,
+    pub action: PromptAction,
+    /// Directory that relative paths are completed against with Tab; no completion if unset.
+    pub completion_base: Option<PathBuf>,
+}
+
+impl Prompt {
+    pub fn new(title: impl Into<String>, initial: impl Into<String>, action: PromptAction) -> Self {
+        let input = initial.into();
+        Self {
+            title: title.into(),
+            cursor: input.chars().count(),
+            input,
+            action,
+            completion_base: None,
+        }
+    }
+
+    /// Enable Tab completion of paths relative to `base`.
+    pub fn with_path_completion(mut self, base: PathBuf) -> Self {
+        self.completion_base = Some(base);
+        self
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptResult {
+        match key.code {
+            KeyCode::Enter => return PromptResult::Submit(self.input.clone()),
+            KeyCode::Esc => return PromptResult::Cancel,
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let idx = self.byte_index(self.cursor);
+                self.input.insert(idx, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                let idx = self.byte_index(self.cursor - 1);
+                self.input.remove(idx);
+                self.cursor -= 1;
+            }
+            KeyCode::Delete if self.cursor < self.input.chars().count() => {
+                let idx = self.byte_index(self.cursor);
+                self.input.remove(idx);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Tab => {
+                if let Some(completed) = self
+                    .completion_base
+                    .as_deref()
+                    .and_then(|base| complete_path(&self.input, base))
+                {
+                    self.input = completed;
+                    self.cursor = self.input.chars().count();
+                }
+            }
+            _ => {}
+        }
+        PromptResult::Pending
+    }
+
+    /// Insert pasted `text` at the cursor, up to its first line break.
+    pub fn insert_str(&mut self, text: &str) {
+        let text = text.lines().next().unwrap_or_default();
+        let idx = self.byte_index(self.cursor);
+        self.input.insert_str(idx, text);
+        self.cursor += text.chars().count();
+    }
+
+    fn byte_index(&self, char_idx: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(char_idx)
+            .map_or(self.input.len(), |(i, _)| i)
+    }
+}
+
+/// Complete the last component of `input` to the longest prefix shared by all matching
+/// entries, appending a separator when it names a single directory.
+fn complete_path(input: &str, base: &Path) -> Option<String> {
+    let (dir, prefix) = match input.rfind(std::path::is_separator) {
+        Some(i) => input.split_at(i + 1),
+        None => ("", input),
+    };
+
+    let mut matches: Vec<(String, bool)> = std::fs::read_dir(base.join(dir))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let is_dir = entry.path().is_dir();
+            name.starts_with(prefix).then_some((name, is_dir))
+        })
+        .collect();
+    matches.sort();
+
+    let (first, first_is_dir) = matches.first()?;
+    let mut common = first.clone();
+    for (name, _) in &matches[1..] {
+        let shared = common
+            .chars()
+            .zip(name.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        common.truncate(shared);
+    }
+    if matches.len() == 1 && *first_is_dir {
+        common.push(std::path::MAIN_SEPARATOR);
+    }
+    Some(format!("{dir}{common}"))
+}
+
+/// What a [`Confirm`] dialog is asking about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmAction {
+    Delete {
+        path: PathBuf,
+    },
+    /// Save the current tab to `path`, replacing the existing file.
+    OverwriteOnSave {
+        path: PathBuf,
+    },
+    /// Create the missing parent directories of `path`, then save the current tab there.
+    CreateDirsOnSave {
+        path: PathBuf,
+    },
+    /// Close the tab at `index` although it has unsaved changes.
+    CloseTab {
+        index: usize,
+    },
+    /// Quit although some tabs have unsaved changes.
+    Quit,
+    /// Open `path` although it looks like a binary file.
+    OpenBinary {
+        path: PathBuf,
+    },
+    /// The modified tab showing `path` was changed on disk by another program.
+    ExternalChange {
+        path: PathBuf,
+    },
+    /// Recover the buffer saved in the swap file `swap` by an earlier session.
+    RecoverSwap {
+        swap: PathBuf,
+    },
+}
+
+/// A question answered by pressing one of a few keys.
+#[derive(Debug, Clone)]
+pub struct Confirm {
+    pub message: String,
+    pub choices: Vec<(char, String)>,
+    pub action: ConfirmAction,
+}
+
+impl Confirm {
+    pub fn new(
+        message: impl Into<String>,
+        choices: &[(char, &str)],
+        action: ConfirmAction,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            choices: choices
+                .iter()
+                .map(|&(key, label)| (key, label.to_string()))
+                .collect(),
+            action,
+        }
+    }
+
+    /// A yes/no question.
+    pub fn yes_no(message: impl Into<String>, action: ConfirmAction) -> Self {
+        Self::new(message, &[('y', "Yes"), ('n', "No")], action)
+    }
+
+    /// The choice selected by `key`, if any.
+    pub fn choice_for(&self, key: KeyEvent) -> Option<char> {
+        match key.code {
+            KeyCode::Char(c) => {
+                let c = c.to_ascii_lowercase();
+                self.choices.iter().find(|(k, _)| *k == c).map(|(k, _)| *k)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_editing() {
+        let action = PromptAction::NewFile {
+            dir: PathBuf::from("."),
+        };
+        let mut prompt = Prompt::new("New file", "ab", action);
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        prompt.handle_key(key(KeyCode::Left));
+        prompt.handle_key(key(KeyCode::Char('é')));
+        prompt.handle_key(key(KeyCode::End));
+        prompt.handle_key(key(KeyCode::Backspace));
+        assert_eq!(
+            prompt.handle_key(key(KeyCode::Enter)),
+            PromptResult::Submit("aé".to_string())
+        );
+        assert_eq!(prompt.handle_key(key(KeyCode::Esc)), PromptResult::Cancel);
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_complete_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src").join("main.rs"), "").unwrap();
+        std::fs::write(dir.join("src").join("mod_a.rs"), "").unwrap();
+        std::fs::write(dir.join("src").join("mod_b.rs"), "").unwrap();
+
+        let sep = std::path::MAIN_SEPARATOR;
+        assert_eq!(complete_path("sr", &dir), Some(format!("src{sep}")));
+        assert_eq!(complete_path("src/mo", &dir), Some("src/mod_".to_string()));
+        assert_eq!(
+            complete_path("src/ma", &dir),
+            Some("src/main.rs".to_string())
+        );
+        assert_eq!(complete_path("nothing", &dir), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
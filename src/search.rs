@@ -0,0 +1,201 @@
+use crate::app::BufferId;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ropey::Rope;
+use std::ops::Range;
+
+/// Outcome of a key press in the [`Search`] bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchResult {
+    Pending,
+    /// The query or an option changed, so the matches need to be found again.
+    Changed,
+    Next,
+    Previous,
+    Close,
+}
+
+/// Incremental search through one buffer.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub query: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Buffer being searched.
+    pub buffer: BufferId,
+    /// Char ranges of all matches, in order.
+    pub matches: Vec<Range<usize>>,
+    /// Index into `matches` of the match at the cursor.
+    pub current: Option<usize>,
+    /// Char index of the cursor when the search started; typing searches from here.
+    pub origin: usize,
+}
+
+impl Search {
+    pub fn new(buffer: BufferId, origin: usize) -> Self {
+        Self {
+            query: String::new(),
+            case_sensitive: false,
+            whole_word: false,
+            buffer,
+            matches: Vec::new(),
+            current: None,
+            origin,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> SearchResult {
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::Esc => SearchResult::Close,
+            KeyCode::Enter | KeyCode::Down => SearchResult::Next,
+            KeyCode::Up => SearchResult::Previous,
+            KeyCode::F(3) if shift => SearchResult::Previous,
+            KeyCode::F(3) => SearchResult::Next,
+            KeyCode::Char('c') if alt => {
+                self.case_sensitive = !self.case_sensitive;
+                SearchResult::Changed
+            }
+            KeyCode::Char('w') if alt => {
+                self.whole_word = !self.whole_word;
+                SearchResult::Changed
+            }
+            KeyCode::Char(c) if !alt && !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.query.push(c);
+                SearchResult::Changed
+            }
+            KeyCode::Backspace if self.query.pop().is_some() => SearchResult::Changed,
+            _ => SearchResult::Pending,
+        }
+    }
+
+    /// Append pasted `text` to the query, up to its first line break.
+    pub fn insert_str(&mut self, text: &str) -> SearchResult {
+        self.query.push_str(text.lines().next().unwrap_or_default());
+        SearchResult::Changed
+    }
+
+    /// Find the matches in `content` again and make the first one at or after `from` the
+    /// current one, wrapping around to the first match.
+    pub fn update(&mut self, content: &Rope, from: usize) {
+        self.matches = find_matches(content, &self.query, self.case_sensitive, self.whole_word);
+        self.current = match self.matches.iter().position(|m| m.start >= from) {
+            Some(index) => Some(index),
+            None => (!self.matches.is_empty()).then_some(0),
+        };
+    }
+
+    /// Move to the next or previous match, wrapping around at either end. Refining the
+    /// query afterwards searches from there.
+    pub fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        let current = match (self.current, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+        self.current = Some(current);
+        self.origin = self.matches[current].start;
+    }
+
+    pub fn current_match(&self) -> Option<Range<usize>> {
+        self.current.map(|i| self.matches[i].clone())
+    }
+
+    /// Position among the matches, e.g. "3 of 17".
+    pub fn counter(&self) -> String {
+        match (self.current, self.matches.len()) {
+            (_, 0) if self.query.is_empty() => String::new(),
+            (_, 0) => "No matches".to_string(),
+            (Some(i), n) => format!("{} of {n}", i + 1),
+            (None, n) => format!("{n} matches"),
+        }
+    }
+}
+
+/// Char ranges of the occurrences of `query` in `content`. Matches do not overlap and
+/// never span lines; with `whole_word` they must not be part of a longer word.
+pub fn find_matches(
+    content: &Rope,
+    query: &str,
+    case_sensitive: bool,
+    whole_word: bool,
+) -> Vec<Range<usize>> {
+    let fold = |c: char| -> char {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return matches;
+    }
+
+    for (row, line) in content.lines().enumerate() {
+        let line: Vec<char> = line.chars().map(fold).collect();
+        let line_start = content.line_to_char(row);
+        let mut start = 0;
+        while start + query.len() <= line.len() {
+            let end = start + query.len();
+            let found = line[start..end] == query[..]
+                && (!whole_word
+                    || (!line[..start].last().is_some_and(|&c| is_word_char(c))
+                        && !line.get(end).is_some_and(|&c| is_word_char(c))));
+            if found {
+                matches.push(line_start + start..line_start + end);
+                start = end;
+            } else {
+                start += 1;
+            }
+        }
+    }
+    matches
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches_with_case_and_whole_word() {
+        let content = Rope::from("Foo foo food\nfoo_bar FOO\n");
+        assert_eq!(find_matches(&content, "foo", false, false).len(), 5);
+        assert_eq!(
+            find_matches(&content, "foo", true, false),
+            [4..7, 8..11, 13..16]
+        );
+        assert_eq!(
+            find_matches(&content, "foo", false, true),
+            [0..3, 4..7, 21..24]
+        );
+        assert!(find_matches(&content, "aa", false, false).is_empty());
+        assert_eq!(
+            find_matches(&Rope::from("aaaa"), "aa", false, false),
+            [0..2, 2..4]
+        );
+    }
+
+    #[test]
+    fn test_step_wraps_around() {
+        let mut search = Search::new(0, 5);
+        search.query = "a".to_string();
+        search.update(&Rope::from("a a a a"), 5);
+        assert_eq!(search.counter(), "4 of 4");
+        search.step(true);
+        assert_eq!(search.current_match(), Some(0..1));
+        search.step(false);
+        search.step(false);
+        assert_eq!(search.counter(), "3 of 4");
+    }
+}
@@ -0,0 +1,104 @@
+use crate::editor::Position;
+use crate::file_ops;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Open tabs and panel state of a project directory, restored on the next launch there.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub tabs: Vec<TabSession>,
+    pub current_tab: usize,
+    pub explorer: ExplorerSession,
+    pub show_file_explorer: bool,
+}
+
+/// A tab showing a file, with its cursor and scroll position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabSession {
+    pub path: PathBuf,
+    pub cursor: Position,
+    pub scroll_offset: Position,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExplorerSession {
+    /// Expanded directories, as absolute paths.
+    pub expanded: Vec<PathBuf>,
+    pub selected_index: usize,
+    pub scroll_offset: usize,
+    pub show_ignored: bool,
+}
+
+/// Directory holding one session file per project directory.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Cannot create session directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    /// The store in `$XDG_STATE_HOME/tui_editor/sessions`, or
+    /// `~/.local/state/tui_editor/sessions`.
+    pub fn open_default() -> Result<Self> {
+        Self::new(file_ops::state_dir()?.join("sessions"))
+    }
+
+    fn path_for(&self, project: &Path) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", file_ops::path_key(project)))
+    }
+
+    /// The session saved for `project`, if there is a readable one.
+    pub fn load(&self, project: &Path) -> Option<Session> {
+        let text = fs::read_to_string(self.path_for(project)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    pub fn save(&self, project: &Path, session: &Session) -> Result<()> {
+        let text = serde_json::to_string_pretty(session)?;
+        file_ops::write_atomic(
+            &self.path_for(project),
+            text.as_bytes(),
+            &Default::default(),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_stored_per_project() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_session_{}", std::process::id()));
+        let store = SessionStore::new(dir.clone()).unwrap();
+        let session = Session {
+            tabs: vec![TabSession {
+                path: dir.join("main.rs"),
+                cursor: Position::new(3, 7),
+                scroll_offset: Position::new(1, 0),
+            }],
+            current_tab: 0,
+            explorer: ExplorerSession {
+                expanded: vec![dir.clone()],
+                selected_index: 2,
+                scroll_offset: 0,
+                show_ignored: true,
+            },
+            show_file_explorer: false,
+        };
+        store.save(&dir.join("project"), &session).unwrap();
+
+        assert_eq!(store.load(&dir.join("project")), Some(session));
+        assert_eq!(store.load(&dir.join("other")), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+use crate::file_ops;
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// First line of every swap file.
+const MAGIC: &str = "tui_editor swap";
+
+/// Directory holding the swap files of unsaved buffers, so their contents survive a crash.
+/// Every editor process holds a lock on `<pid>.lock` in it while running, which tells
+/// other processes whether its swap files are still in use.
+#[derive(Debug, Clone)]
+pub struct SwapStore {
+    dir: PathBuf,
+    /// Held while the store is in use.
+    _lock: Option<Arc<LockFile>>,
+}
+
+/// The locked lock file of this process, removed again when the store is dropped.
+#[derive(Debug)]
+struct LockFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // Close the file first, as locked or open files cannot be removed everywhere
+        self.file.take();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The contents of a swap file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapFile {
+    /// Location of the swap file itself.
+    pub swap_path: PathBuf,
+    /// Process that wrote it.
+    pub pid: u32,
+    /// File the buffer belongs to; None for untitled buffers.
+    pub path: Option<PathBuf>,
+    pub content: String,
+}
+
+impl SwapStore {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Cannot create swap directory {}", dir.display()))?;
+        let path = lock_path(&dir, std::process::id());
+        let file = File::create(&path)
+            .with_context(|| format!("Cannot create lock file {}", path.display()))?;
+        // Another store of this process may hold the lock already
+        let lock = file.try_lock().is_ok().then(|| {
+            Arc::new(LockFile {
+                path,
+                file: Some(file),
+            })
+        });
+        Ok(Self { dir, _lock: lock })
+    }
+
+    /// The store in `$XDG_STATE_HOME/tui_editor/swap`, or `~/.local/state/tui_editor/swap`.
+    pub fn open_default() -> Result<Self> {
+        Self::new(file_ops::state_dir()?.join("swap"))
+    }
+
+    /// Swap file name for the buffer of `path`, or for untitled buffer number `untitled`.
+    /// Names include the process id, so that instances editing the same file never write
+    /// over each other's swap files or a leftover of a crashed one.
+    pub fn name_for(&self, path: Option<&Path>, untitled: usize) -> String {
+        let pid = std::process::id();
+        match path {
+            Some(path) => format!("{}-{pid}.swp", file_ops::path_key(path)),
+            None => format!("untitled-{pid}-{untitled}.swp"),
+        }
+    }
+
+    /// Write the swap file `name` for a buffer of `path` holding `content`.
+    pub fn write(&self, name: &str, path: Option<&Path>, content: &str) -> Result<()> {
+        let mut text = format!("{MAGIC}\npid: {}\n", std::process::id());
+        if let Some(path) = path {
+            let path = file_ops::absolute(path);
+            text.push_str(&format!("path: {}\n", path.display()));
+        }
+        text.push('\n');
+        text.push_str(content);
+        file_ops::write_atomic(&self.dir.join(name), text.as_bytes(), &Default::default())?;
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) {
+        let _ = fs::remove_file(self.dir.join(name));
+    }
+
+    /// Swap files left behind by editor processes that are known to have exited. The lock
+    /// files of exited processes without swap files left are removed.
+    pub fn leftovers(&self) -> Vec<SwapFile> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        let mut swaps: Vec<SwapFile> = paths
+            .iter()
+            .filter_map(|path| read(path))
+            .filter(|swap| {
+                swap.pid != std::process::id() && self.process_alive(swap.pid) == Some(false)
+            })
+            .collect();
+        swaps.sort_by(|a, b| a.swap_path.cmp(&b.swap_path));
+
+        for path in paths
+            .iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "lock"))
+        {
+            let pid = path
+                .file_stem()
+                .and_then(|s| s.to_str()?.parse::<u32>().ok());
+            if let Some(pid) = pid.filter(|&pid| pid != std::process::id()) {
+                if self.process_alive(pid) == Some(false) && !swaps.iter().any(|s| s.pid == pid) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+        swaps
+    }
+
+    /// A leftover swap file for the buffer of `path`, if there is one.
+    pub fn leftover_for(&self, path: &Path) -> Option<SwapFile> {
+        let path = file_ops::absolute(path);
+        self.leftovers()
+            .into_iter()
+            .find(|swap| swap.path.as_ref() == Some(&path))
+    }
+
+    /// Whether the editor process `pid` is still running, judged by its lock file, or None
+    /// if that cannot be told.
+    fn process_alive(&self, pid: u32) -> Option<bool> {
+        match File::open(lock_path(&self.dir, pid)) {
+            Ok(file) => match file.try_lock() {
+                Ok(()) => Some(false),
+                Err(fs::TryLockError::WouldBlock) => Some(true),
+                Err(fs::TryLockError::Error(_)) => None,
+            },
+            // Written by a version without lock files, or the lock file was removed
+            Err(_) if cfg!(target_os = "linux") => {
+                Some(Path::new("/proc").join(pid.to_string()).exists())
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+fn lock_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{pid}.lock"))
+}
+
+/// Parse the swap file at `swap_path`.
+pub fn read(swap_path: &Path) -> Option<SwapFile> {
+    let text = fs::read_to_string(swap_path).ok()?;
+    let (header, content) = text.split_once("\n\n")?;
+    let mut lines = header.lines();
+    if lines.next()? != MAGIC {
+        return None;
+    }
+    let mut pid = None;
+    let mut path = None;
+    for line in lines {
+        match line.split_once(": ") {
+            Some(("pid", value)) => pid = value.parse().ok(),
+            Some(("path", value)) => path = Some(PathBuf::from(value)),
+            _ => {}
+        }
+    }
+    Some(SwapFile {
+        swap_path: swap_path.to_path_buf(),
+        pid: pid?,
+        path,
+        content: content.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_files_round_trip_and_leftovers() {
+        let dir = std::env::temp_dir().join(format!("tui_editor_swap_{}", std::process::id()));
+        let store = SwapStore::new(dir.clone()).unwrap();
+        let file = dir.join("notes.txt");
+        let name = store.name_for(Some(&file), 0);
+        store.write(&name, Some(&file), "line\n\nmore").unwrap();
+
+        let swap = read(&dir.join(&name)).unwrap();
+        assert_eq!(swap.path.as_deref(), Some(file.as_path()));
+        assert_eq!(swap.content, "line\n\nmore");
+        // Swap files of this process are not leftovers
+        assert!(store.leftovers().is_empty());
+        assert!(store.leftover_for(&file).is_none());
+
+        // Another running instance's swap files are left alone
+        let text = fs::read_to_string(dir.join(&name)).unwrap();
+        let pid = format!("pid: {}", std::process::id());
+        fs::write(dir.join(&name), text.replace(&pid, "pid: 4294967295")).unwrap();
+        let other = File::create(lock_path(&dir, 4294967295)).unwrap();
+        other.try_lock().unwrap();
+        assert!(store.leftovers().is_empty());
+
+        // Pretend the writer died
+        drop(other);
+        assert_eq!(store.leftovers().len(), 1);
+        assert_eq!(store.leftover_for(&file).unwrap().content, "line\n\nmore");
+
+        store.remove(&name);
+        assert!(store.leftovers().is_empty());
+        assert!(!lock_path(&dir, 4294967295).exists());
+        drop(store);
+        assert!(!lock_path(&dir, std::process::id()).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
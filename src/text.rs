@@ -0,0 +1,158 @@
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Chars that end a line for [`Rope`], besides the CRLF pair.
+const LINE_BREAKS: [char; 7] = [
+    '\n', '\r', '\u{0B}', '\u{0C}', '\u{85}', '\u{2028}', '\u{2029}',
+];
+
+/// Number of chars of line `row` of `content`, not counting its line break.
+pub fn line_len(content: &Rope, row: usize) -> usize {
+    let line = content.line(row);
+    let len = line.len_chars();
+    if len >= 2 && line.char(len - 2) == '\r' && line.char(len - 1) == '\n' {
+        len - 2
+    } else if len >= 1 && LINE_BREAKS.contains(&line.char(len - 1)) {
+        len - 1
+    } else {
+        len
+    }
+}
+
+/// Text of line `row` of `content` without its line break.
+pub fn line_content(content: &Rope, row: usize) -> String {
+    content
+        .line(row)
+        .slice(..line_len(content, row))
+        .to_string()
+}
+
+/// Number of terminal columns taken by the grapheme cluster `grapheme` when it starts at
+/// display column `column`. Tabs extend to the next tab stop; line breaks and other
+/// control characters take no space.
+pub fn grapheme_width(grapheme: &str, column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - column % tab_width
+    } else if grapheme.starts_with(char::is_control) {
+        0
+    } else {
+        grapheme.width()
+    }
+}
+
+/// The grapheme clusters of `line` with the char index and display column each starts at.
+pub fn graphemes(line: &str, tab_width: usize) -> impl Iterator<Item = Grapheme<'_>> {
+    let mut col = 0;
+    let mut column = 0;
+    line.graphemes(true).map(move |text| {
+        let grapheme = Grapheme {
+            text,
+            col,
+            column,
+            width: grapheme_width(text, column, tab_width),
+        };
+        col += text.chars().count();
+        column += grapheme.width;
+        grapheme
+    })
+}
+
+/// A grapheme cluster placed on a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grapheme<'a> {
+    pub text: &'a str,
+    /// Char index of the first char of the cluster.
+    pub col: usize,
+    /// Display column the cluster starts at.
+    pub column: usize,
+    pub width: usize,
+}
+
+/// Display column of char index `col` in `line`. Indices inside a cluster map to its start.
+pub fn display_column(line: &str, col: usize, tab_width: usize) -> usize {
+    let mut end = 0;
+    for g in graphemes(line, tab_width) {
+        if g.col + g.text.chars().count() > col {
+            return g.column;
+        }
+        end = g.column + g.width;
+    }
+    end
+}
+
+/// Char index of the cluster covering display column `column` in `line`, or the length of
+/// the line in chars when the column lies past its end.
+pub fn col_at_display(line: &str, column: usize, tab_width: usize) -> usize {
+    let mut end = 0;
+    for g in graphemes(line, tab_width) {
+        if g.column + g.width > column {
+            return g.col;
+        }
+        end = g.col + g.text.chars().count();
+    }
+    end
+}
+
+/// Char index of the cluster boundary after char index `col`.
+pub fn next_grapheme(line: &str, col: usize) -> usize {
+    graphemes(line, 1)
+        .map(|g| g.col + g.text.chars().count())
+        .find(|&end| end > col)
+        .unwrap_or(col)
+}
+
+/// Char index of the cluster boundary before char index `col`.
+pub fn prev_grapheme(line: &str, col: usize) -> usize {
+    graphemes(line, 1)
+        .map(|g| g.col)
+        .take_while(|&start| start < col)
+        .last()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_content_excludes_line_breaks() {
+        for (text, break_len) in [("ab\ncd", 1), ("ab\r\ncd", 2), ("ab\rcd", 1)] {
+            let content = Rope::from(text);
+            assert_eq!(content.len_lines(), 2, "{text:?}");
+            assert_eq!(line_len(&content, 0), 2);
+            assert_eq!(line_content(&content, 0), "ab");
+            assert_eq!(line_content(&content, 1), "cd");
+            assert_eq!(content.line_to_char(1), 2 + break_len);
+        }
+        let content = Rope::from("x\n");
+        assert_eq!(line_content(&content, 1), "");
+    }
+
+    #[test]
+    fn test_display_columns_expand_tabs_and_wide_chars() {
+        let line = "\tab日本x";
+        assert_eq!(display_column(line, 0, 4), 0);
+        assert_eq!(display_column(line, 1, 4), 4);
+        assert_eq!(display_column(line, 4, 4), 8);
+        assert_eq!(display_column(line, 5, 4), 10);
+        assert_eq!(display_column(line, 6, 4), 11);
+        assert_eq!(display_column("ab\tc", 3, 4), 4);
+
+        assert_eq!(col_at_display(line, 2, 4), 0);
+        assert_eq!(col_at_display(line, 9, 4), 4);
+        assert_eq!(col_at_display(line, 100, 4), 6);
+    }
+
+    #[test]
+    fn test_grapheme_steps_skip_combining_marks_and_emoji() {
+        // "e" + combining acute accent, then a family emoji joined with ZWJs
+        let line = "e\u{301}👨\u{200d}👩\u{200d}👧!";
+        assert_eq!(next_grapheme(line, 0), 2);
+        assert_eq!(next_grapheme(line, 2), 7);
+        assert_eq!(prev_grapheme(line, 7), 2);
+        assert_eq!(prev_grapheme(line, 2), 0);
+        assert_eq!(display_column(line, 7, 4), 3);
+    }
+}
@@ -1,12 +1,26 @@
-use crate::app::App;
+use crate::app::{App, Buffer, Focus, PaneArea};
+use crate::editor::Editor;
+use crate::finder::Finder;
 use crate::highlight::Highlighter;
+use crate::prompt::{Confirm, Prompt};
+use crate::search::Search;
+use crate::text;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Tabs},
     Frame,
 };
+use std::cmp;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Width of the line number gutter in the editor.
+const GUTTER_WIDTH: u16 = 5;
+/// Columns kept free on either side of a scrolled tab bar for the overflow markers.
+const TAB_INDICATOR_WIDTH: u16 = 2;
 
 pub fn ui(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
@@ -14,25 +28,43 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         .constraints([
             Constraint::Length(3), // Tabs
             Constraint::Min(0),    // Main content
-            Constraint::Length(1), // Status bar
+            Constraint::Length(2), // Status bar
         ])
         .split(f.area());
 
-    render_tabs(f, app, chunks[0]);
+    app.areas.tabs = chunks[0];
+    app.areas.tab_titles = render_tabs(f, app, chunks[0]);
     render_main_content(f, app, chunks[1]);
     render_status_bar(f, app, chunks[2]);
+
+    if let Some(finder) = &mut app.finder {
+        render_finder(f, finder);
+    } else if let Some(confirm) = &app.confirm {
+        render_confirm(f, confirm);
+    } else if let Some(prompt) = &app.prompt {
+        render_prompt(f, prompt);
+    }
 }
 
-fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
+/// Render the tab bar, returning the horizontal extent of each tab title. When the titles
+/// do not fit, the bar scrolls to keep the current tab visible and marks hidden tabs.
+fn render_tabs(f: &mut Frame, app: &mut App, area: Rect) -> Vec<(u16, u16)> {
     let tab_titles: Vec<String> = app
-        .tabs
-        .iter()
+        .tab_labels()
+        .into_iter()
+        .zip(&app.tabs)
         .enumerate()
-        .map(|(i, tab)| {
-            let mut name = tab.name.clone();
-            if tab.modified {
+        .map(|(i, (mut name, tab))| {
+            let buffer = &app.buffers[tab.buffer];
+            if buffer.modified {
                 name.push_str(" *");
             }
+            if buffer.orphaned {
+                name.push_str(" [deleted]");
+            }
+            if buffer.is_new {
+                name.push_str(" [New]");
+            }
             if i == app.current_tab {
                 format!("▶ {name}")
             } else {
@@ -41,8 +73,54 @@ fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
 
-    let tabs = Tabs::new(tab_titles)
-        .block(Block::default().borders(Borders::BOTTOM))
+    let block = Block::default()
+        .borders(Borders::BOTTOM)
+        .style(Style::default().fg(Color::Cyan));
+    let bar = block.inner(area);
+    f.render_widget(block, area);
+
+    // Tabs pads every title with a space on each side and separates them with a divider
+    let widths: Vec<u16> = tab_titles
+        .iter()
+        .map(|title| Span::raw(title.as_str()).width() as u16 + 3)
+        .collect();
+    let total: u32 = widths.iter().map(|&w| w as u32).sum();
+    let (bar, visible) = if total <= bar.width as u32 + 1 {
+        app.tab_scroll = 0;
+        (bar, 0..tab_titles.len())
+    } else {
+        let inner = Rect {
+            x: bar.x + TAB_INDICATOR_WIDTH,
+            width: bar.width.saturating_sub(2 * TAB_INDICATOR_WIDTH),
+            ..bar
+        };
+        let visible = visible_tabs(&widths, app.current_tab, &mut app.tab_scroll, inner.width);
+        let indicator = Style::default().fg(Color::DarkGray);
+        if visible.start > 0 {
+            let left = Rect { width: 1, ..bar };
+            f.render_widget(Paragraph::new(Span::styled("«", indicator)), left);
+        }
+        if visible.end < tab_titles.len() {
+            let right = Rect {
+                x: bar.right().saturating_sub(1),
+                width: 1,
+                ..bar
+            };
+            f.render_widget(Paragraph::new(Span::styled("»", indicator)), right);
+        }
+        (inner, visible)
+    };
+
+    let mut title_ranges = vec![(0, 0); tab_titles.len()];
+    let mut x = bar.x;
+    for i in visible.clone() {
+        let end = x.saturating_add(widths[i] - 1).min(bar.right());
+        title_ranges[i] = (x, end);
+        x = end.saturating_add(1);
+    }
+
+    let tabs = Tabs::new(tab_titles[visible.clone()].to_vec())
+        .select(app.current_tab.saturating_sub(visible.start))
         .style(Style::default().fg(Color::Cyan))
         .highlight_style(
             Style::default()
@@ -50,7 +128,31 @@ fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
                 .add_modifier(Modifier::BOLD),
         );
 
-    f.render_widget(tabs, area);
+    f.render_widget(tabs, bar);
+    title_ranges
+}
+
+/// The tabs to show in `width` columns given the width of each title including its
+/// divider, scrolling as little as needed from `scroll` to keep `current` visible.
+fn visible_tabs(widths: &[u16], current: usize, scroll: &mut usize, width: u16) -> Range<usize> {
+    // The last title shown has no divider
+    let fits = |range: Range<usize>| {
+        widths[range].iter().map(|&w| w as u32).sum::<u32>() <= width as u32 + 1
+    };
+    let current = current.min(widths.len().saturating_sub(1));
+    *scroll = (*scroll).min(current);
+    while *scroll < current && !fits(*scroll..current + 1) {
+        *scroll += 1;
+    }
+    let mut end = (current + 1).min(widths.len());
+    while end < widths.len() && fits(*scroll..end + 1) {
+        end += 1;
+    }
+    // Use space freed at the end, e.g. after closing tabs, for tabs on the left
+    while end == widths.len() && *scroll > 0 && fits(*scroll - 1..end) {
+        *scroll -= 1;
+    }
+    *scroll..end
 }
 
 fn render_main_content(f: &mut Frame, app: &mut App, area: Rect) {
@@ -78,65 +180,128 @@ fn render_main_content(f: &mut Frame, app: &mut App, area: Rect) {
     } else {
         chunks[0]
     };
+    app.areas.explorer = app.show_file_explorer.then_some(chunks[0]);
+    app.areas.panes.clear();
+    let split = app.panes.len() > 1;
     let highlighter = &app.highlighter;
-    let tab = app.get_current_tab();
-    render_editor(f, tab, editor_area, highlighter);
+    // Search matches are highlighted in every view of the searched buffer
+    let matches = |buffer| match &app.search {
+        Some(search) if search.buffer == buffer => search.matches.as_slice(),
+        _ => &[],
+    };
+    for (id, area) in app.panes.areas(editor_area) {
+        let text = if id == app.panes.focused() {
+            let view = app.tabs.get_mut(app.current_tab).map(|tab| {
+                (
+                    &app.buffers[tab.buffer],
+                    &mut tab.editor,
+                    matches(tab.buffer),
+                )
+            });
+            let text = render_editor(f, view, area, highlighter, true, split);
+            app.areas.editor = area;
+            app.areas.editor_text = text;
+            text
+        } else {
+            let Some(pane) = app.panes.get_mut(id) else {
+                continue;
+            };
+            let view = app.tabs.get(pane.tab).map(|tab| {
+                (
+                    &app.buffers[tab.buffer],
+                    &mut pane.editor,
+                    matches(tab.buffer),
+                )
+            });
+            render_editor(f, view, area, highlighter, false, false)
+        };
+        app.areas.panes.push(PaneArea { id, area, text });
+    }
 }
 
 fn render_file_explorer(f: &mut Frame, app: &mut App, area: Rect) {
-    let files = app.file_explorer.get_display_lines();
-    let items: Vec<ListItem> = files
+    let focused = app.focus == Focus::Explorer;
+    let height = area.height.saturating_sub(2) as usize;
+    app.file_explorer.ensure_selected_visible(height);
+
+    let nodes = app.file_explorer.visible_nodes();
+    let items: Vec<ListItem> = nodes
         .iter()
         .enumerate()
-        .map(|(i, file)| {
+        .skip(app.file_explorer.scroll_offset)
+        .take(height)
+        .map(|(i, (depth, node))| {
             let style = if i == app.file_explorer.selected_index {
-                Style::default()
+                let style = Style::default()
                     .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::BOLD);
+                if focused {
+                    style.bg(Color::DarkGray)
+                } else {
+                    style
+                }
+            } else if node.ignored {
+                Style::default().fg(Color::DarkGray)
             } else {
                 Style::default().fg(Color::White)
             };
-            ListItem::new(file.clone()).style(style)
+            ListItem::new(node.display_line(*depth)).style(style)
         })
         .collect();
 
+    let border_color = if focused { Color::Yellow } else { Color::Cyan };
     let list = List::new(items)
         .block(
             Block::default()
                 .title("Files")
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::Cyan)),
+                .style(Style::default().fg(border_color)),
         )
         .style(Style::default().fg(Color::White));
 
     f.render_widget(list, area);
 }
 
+/// Render a buffer through a view onto it with the given search matches highlighted,
+/// placing the terminal cursor when `focused`. `highlight_border` marks the focused one of
+/// several panes.
 fn render_editor(
     f: &mut Frame,
-    tab: Option<&crate::app::Tab>,
+    view: Option<(&Buffer, &mut Editor, &[Range<usize>])>,
     area: Rect,
     highlighter: &Highlighter,
-) {
-    if let Some(tab) = tab {
-        let editor = &tab.editor;
-        let content = &tab.content;
-        let extension = tab
-            .path
-            .as_ref()
-            .and_then(|p| p.extension().and_then(|e| e.to_str()))
-            .unwrap_or("");
+    focused: bool,
+    highlight_border: bool,
+) -> Rect {
+    // Text area inside the border and to the right of the line numbers
+    let text_area = Rect {
+        x: area.x + 1 + GUTTER_WIDTH,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2 + GUTTER_WIDTH),
+        height: area.height.saturating_sub(2),
+    };
+
+    if let Some((buffer, editor, matches)) = view {
+        let Buffer {
+            content,
+            name,
+            syntax,
+            ..
+        } = buffer;
+        // The text may have been edited through another view
+        editor.clamp_to(content);
 
         // Ensure cursor is visible
-        let mut editor_clone = editor.clone();
-        editor_clone.ensure_cursor_visible(content, area.width as usize, area.height as usize);
+        editor.ensure_cursor_visible(content, text_area.width as usize, text_area.height as usize);
+        let editor = &*editor;
 
         // Get visible lines
-        let visible_lines = editor_clone.get_visible_lines(content, area.height as usize);
+        let visible_lines = editor.get_visible_lines(content, text_area.height as usize);
 
         // Create line numbers and content
         let mut display_lines = Vec::new();
-        let start_line = editor_clone.scroll_offset.row;
+        let start_line = editor.scroll_offset.row;
+        let selection = editor.selection_range(content);
 
         for (i, line) in visible_lines.iter().enumerate() {
             let line_num = start_line + i + 1;
@@ -146,56 +311,168 @@ fn render_editor(
                 Style::default().fg(Color::DarkGray),
             )];
             // Add syntax-highlighted line content
-            let highlighted = highlighter.highlight_line(line, extension);
-            spans.extend(highlighted);
+            let mut highlighted = highlighter.highlight_line(line, syntax);
+            let line_start = content.line_to_char(start_line + i);
+            let line_len = line.chars().count();
+            let first = matches.partition_point(|m| m.end <= line_start);
+            for found in matches[first..]
+                .iter()
+                .take_while(|m| m.start < line_start + line_len)
+            {
+                let start = found.start.saturating_sub(line_start);
+                let end = (found.end - line_start).min(line_len);
+                highlighted = style_char_range(highlighted, start, end, match_style());
+            }
+            if let Some((sel_start, sel_end)) = selection {
+                let start = sel_start.saturating_sub(line_start).min(line_len);
+                let end = sel_end.saturating_sub(line_start).min(line_len);
+                highlighted = style_char_range(highlighted, start, end, selection_style());
+            }
+            spans.extend(crop_columns(
+                highlighted,
+                editor.scroll_offset.col,
+                text_area.width as usize,
+                editor.indent.width,
+            ));
             display_lines.push(Line::from(spans));
         }
 
+        let border_color = if highlight_border {
+            Color::Yellow
+        } else {
+            Color::Cyan
+        };
         let paragraph = Paragraph::new(display_lines)
             .block(
                 Block::default()
-                    .title(format!(" {} ", tab.name))
+                    .title(format!(" {name} "))
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::Cyan)),
+                    .style(Style::default().fg(border_color)),
             )
-            .style(Style::default().fg(Color::White))
-            .wrap(Wrap { trim: true });
+            .style(Style::default().fg(Color::White));
 
         f.render_widget(paragraph, area);
 
         // Render cursor
         let cursor_x = editor
-            .cursor
-            .col
-            .saturating_sub(editor_clone.scroll_offset.col);
-        let cursor_y = editor
-            .cursor
-            .row
-            .saturating_sub(editor_clone.scroll_offset.row);
-
-        if cursor_y < area.height.saturating_sub(2) as usize
-            && cursor_x < area.width.saturating_sub(6) as usize
-        {
-            f.set_cursor_position((
-                area.x + cursor_x as u16 + 5, // +5 for line numbers
-                area.y + cursor_y as u16 + 1, // +1 for border
-            ));
+            .cursor_column(content)
+            .saturating_sub(editor.scroll_offset.col);
+        let cursor_y = editor.cursor.row.saturating_sub(editor.scroll_offset.row);
+
+        if focused && cursor_y < text_area.height as usize && cursor_x < text_area.width as usize {
+            f.set_cursor_position((text_area.x + cursor_x as u16, text_area.y + cursor_y as u16));
+        }
+    }
+
+    text_area
+}
+
+/// Lay out a line made of `spans` on the terminal grid and keep the display columns
+/// `start..start + width`. Tabs become spaces up to the next tab stop and wide chars cut
+/// by either edge are replaced with spaces.
+fn crop_columns(
+    spans: Vec<Span<'static>>,
+    start: usize,
+    width: usize,
+    tab_width: usize,
+) -> Vec<Span<'static>> {
+    let end = start + width;
+    let mut result = Vec::with_capacity(spans.len());
+    let mut column = 0;
+    for span in spans {
+        let mut visible = String::new();
+        for grapheme in span.content.graphemes(true) {
+            let width = text::grapheme_width(grapheme, column, tab_width);
+            let (from, to) = (column.max(start), (column + width).min(end));
+            if from < to {
+                if grapheme == "\t" || from > column || to < column + width {
+                    visible.extend(std::iter::repeat_n(' ', to - from));
+                } else {
+                    visible.push_str(grapheme);
+                }
+            }
+            column += width;
+        }
+        if !visible.is_empty() {
+            result.push(Span::styled(visible, span.style));
+        }
+        if column >= end {
+            break;
         }
     }
+    result
+}
+
+fn selection_style() -> Style {
+    Style::default().bg(Color::Rgb(68, 78, 102))
+}
+
+fn match_style() -> Style {
+    Style::default().bg(Color::Rgb(97, 84, 38))
+}
+
+/// Patch `style` onto the chars `start..end` of a line made of `spans`,
+/// splitting spans at the range boundaries where needed.
+fn style_char_range(
+    spans: Vec<Span<'static>>,
+    start: usize,
+    end: usize,
+    style: Style,
+) -> Vec<Span<'static>> {
+    if start >= end {
+        return spans;
+    }
+
+    let mut result = Vec::with_capacity(spans.len() + 2);
+    let mut offset = 0;
+    for span in spans {
+        let len = span.content.chars().count();
+        let span_start = offset;
+        let span_end = offset + len;
+        offset = span_end;
+
+        if span_end <= start || span_start >= end {
+            result.push(span);
+            continue;
+        }
+
+        let text: Vec<char> = span.content.chars().collect();
+        let local_start = start.saturating_sub(span_start);
+        let local_end = cmp::min(end, span_end) - span_start;
+        let before: String = text[..local_start].iter().collect();
+        let inside: String = text[local_start..local_end].iter().collect();
+        let after: String = text[local_end..].iter().collect();
+        if !before.is_empty() {
+            result.push(Span::styled(before, span.style));
+        }
+        result.push(Span::styled(inside, span.style.patch(style)));
+        if !after.is_empty() {
+            result.push(Span::styled(after, span.style));
+        }
+    }
+    result
 }
 
 fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    if let Some(search) = &app.search {
+        render_search_bar(f, search, area);
+        return;
+    }
+    let tab = app.get_current_tab();
+    let buffer = app.get_current_buffer();
     let status_text = if let Some(message) = &app.status_message {
         format!(" {message} ")
-    } else if let Some(tab) = app.get_current_tab() {
+    } else if let (Some(tab), Some(buffer)) = (tab, buffer) {
         let cursor = &tab.editor.cursor;
-        let total_lines = tab.content.len_lines();
-        let total_chars = tab.content.len_chars();
+        // The display column, so tabs and wide chars count as the columns they take up
+        let column = tab.editor.cursor_column(&buffer.content);
+        let total_lines = buffer.content.len_lines();
+        let total_chars = buffer.content.len_chars();
 
         format!(
             " Line: {}, Col: {} | Lines: {} | Chars: {} ",
             cursor.row + 1,
-            cursor.col + 1,
+            column + 1,
             total_lines,
             total_chars
         )
@@ -203,6 +480,17 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
         " Ready ".to_string()
     };
 
+    // File format on the right, e.g. "UTF-8 | LF"
+    let format_text = buffer.map_or(String::new(), |buffer| {
+        format!(
+            " {} | {} ",
+            buffer.encoding.name(),
+            buffer.line_ending.name()
+        )
+    });
+    let padding = (area.width as usize).saturating_sub(status_text.width() + format_text.width());
+    let status_text = format!("{status_text}{}{format_text}", " ".repeat(padding));
+
     let status_style = Style::default().fg(Color::Black).bg(Color::White);
 
     let status = Paragraph::new(status_text)
@@ -212,6 +500,164 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(status, area);
 }
 
+/// Render the search query in place of the status bar, with the match counter and the
+/// search options on the right, e.g. "3 of 17 | Alt+C Case: off | Alt+W Word: on".
+fn render_search_bar(f: &mut Frame, search: &Search, area: Rect) {
+    let on_off = |on| if on { "on" } else { "off" };
+    let query_text = format!(" Find: {}", search.query);
+    let options_text = format!(
+        " {} | Alt+C Case: {} | Alt+W Word: {} ",
+        search.counter(),
+        on_off(search.case_sensitive),
+        on_off(search.whole_word)
+    );
+    let padding = (area.width as usize).saturating_sub(query_text.width() + options_text.width());
+    let status_text = format!("{query_text}{}{options_text}", " ".repeat(padding));
+
+    let status = Paragraph::new(status_text)
+        .style(Style::default().fg(Color::Black).bg(Color::White))
+        .block(Block::default().borders(Borders::TOP));
+    f.render_widget(status, area);
+    f.set_cursor_position((area.x + query_text.width() as u16, area.y + 1));
+}
+
+fn render_finder(f: &mut Frame, finder: &mut Finder) {
+    let screen = f.area();
+    let area = centered_rect(screen.width * 9 / 10, screen.height * 8 / 10, screen);
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
+
+    let query_block = Block::default()
+        .title(format!(" Open file ({} matches) ", finder.matches.len()))
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::Yellow));
+    let query_inner = query_block.inner(chunks[0]);
+    let query = Paragraph::new(finder.query.as_str())
+        .style(Style::default().fg(Color::White))
+        .block(query_block);
+    f.render_widget(query, chunks[0]);
+    f.set_cursor_position((
+        query_inner.x + Span::raw(finder.query.as_str()).width() as u16,
+        query_inner.y,
+    ));
+
+    let height = panes[0].height.saturating_sub(2) as usize;
+    finder.ensure_selected_visible(height);
+    let match_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let items: Vec<ListItem> = finder
+        .matches
+        .iter()
+        .enumerate()
+        .skip(finder.scroll_offset)
+        .take(height)
+        .map(|(i, m)| {
+            let spans = finder.files[m.file]
+                .chars()
+                .enumerate()
+                .map(|(pos, c)| {
+                    if m.positions.contains(&pos) {
+                        Span::styled(c.to_string(), match_style)
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                })
+                .collect::<Vec<_>>();
+            let style = if i == finder.selected {
+                Style::default().fg(Color::White).bg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(list, panes[0]);
+
+    let preview_height = panes[1].height.saturating_sub(2) as usize;
+    let preview: Vec<Line> = finder
+        .preview()
+        .iter()
+        .take(preview_height)
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    let preview = Paragraph::new(preview)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title(" Preview ")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan)),
+        );
+    f.render_widget(preview, panes[1]);
+}
+
+fn render_prompt(f: &mut Frame, prompt: &Prompt) {
+    let area = centered_rect(60, 3, f.area());
+    let block = Block::default()
+        .title(format!(" {} ", prompt.title))
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::Yellow));
+    let inner = block.inner(area);
+
+    // Keep the cursor visible for inputs longer than the popup
+    let before_cursor: String = prompt.input.chars().take(prompt.cursor).collect();
+    let cursor_width = Span::raw(before_cursor.as_str()).width() as u16;
+    let scroll = cursor_width.saturating_sub(inner.width.saturating_sub(1));
+
+    let input = Paragraph::new(prompt.input.as_str())
+        .style(Style::default().fg(Color::White))
+        .scroll((0, scroll))
+        .block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(input, area);
+    f.set_cursor_position((inner.x + cursor_width - scroll, inner.y));
+}
+
+fn render_confirm(f: &mut Frame, confirm: &Confirm) {
+    let choices: Vec<Span> = confirm
+        .choices
+        .iter()
+        .flat_map(|(key, label)| {
+            [
+                Span::styled(
+                    format!("[{key}]"),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(" {label}  ")),
+            ]
+        })
+        .collect();
+    let text = vec![Line::from(confirm.message.as_str()), Line::from(choices)];
+
+    let area = centered_rect(60, 4, f.area());
+    let dialog = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title(" Confirm ")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Yellow)),
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(dialog, area);
+}
+
 pub fn render_help(f: &mut Frame, app: &App) {
     if !app.show_help {
         return;
@@ -233,7 +679,13 @@ pub fn render_help(f: &mut Frame, app: &App) {
         )]),
         Line::from("  Arrow Keys - Move cursor"),
         Line::from("  Home/End - Line start/end"),
+        Line::from("  Shift+Movement - Extend selection"),
+        Line::from("  Ctrl+A - Select all"),
+        Line::from("  Ctrl+F - Find: Enter/Up/Down next/previous, Alt+C case, Alt+W word"),
         Line::from("  Page Up/Down - Page navigation"),
+        Line::from("  Explorer: Arrows, Enter to open, Esc to leave"),
+        Line::from("  Explorer: n/N new file/dir, r rename, c copy, m move, d delete"),
+        Line::from("  Explorer: i show/hide ignored files"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "File Operations:",
@@ -242,8 +694,13 @@ pub fn render_help(f: &mut Frame, app: &App) {
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from("  Ctrl+N - New file"),
-        Line::from("  Ctrl+O - Open file"),
+        Line::from("  Ctrl+O - Find and open file"),
         Line::from("  Ctrl+S - Save file"),
+        Line::from("  Alt+S - Save as (Tab completes paths)"),
+        Line::from("  Alt+E / Alt+L - Change encoding / line endings"),
+        Line::from("  Ctrl+W / Alt+W - Close tab / other tabs, Alt+T - Reopen"),
+        Line::from("  Ctrl+PgUp/PgDn - Previous / next tab, Alt+1..9 - Go to tab"),
+        Line::from("  Ctrl+Shift+PgUp/PgDn - Move tab left / right"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Editor:",
@@ -251,9 +708,15 @@ pub fn render_help(f: &mut Frame, app: &App) {
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
         )]),
-        Line::from("  Tab - Toggle file explorer"),
+        Line::from("  Ctrl+C / Ctrl+X / Ctrl+V - Copy / Cut / Paste"),
+        Line::from("  Ctrl+Z / Ctrl+Y - Undo / Redo"),
+        Line::from("  Tab / Shift+Tab - Indent / outdent line or selection"),
+        Line::from("  Alt+\\ / Alt+- - Split pane side by side / stacked, Alt+Q - Close pane"),
+        Line::from("  Alt+Arrows - Focus pane, Alt+Shift+Arrows - Resize pane"),
+        Line::from("  Ctrl+B - Toggle file explorer"),
+        Line::from("  Ctrl+E - Focus file explorer / editor"),
         Line::from("  F1 - Toggle this help"),
-        Line::from("  Q - Quit"),
+        Line::from("  Ctrl+Q - Quit (asks about unsaved changes)"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Press any key to close",
@@ -266,12 +729,13 @@ pub fn render_help(f: &mut Frame, app: &App) {
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::Cyan));
 
+    let height = help_text.len() as u16 + 2;
     let help_paragraph = Paragraph::new(help_text)
         .block(help_block)
         .style(Style::default().fg(Color::White));
 
     // Center the help dialog
-    let popup_area = centered_rect(60, 20, f.area());
+    let popup_area = centered_rect(60, height, f.area());
     f.render_widget(help_paragraph, popup_area);
 }
 
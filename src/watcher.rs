@@ -0,0 +1,74 @@
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Watches the directories of open files and reports when something in them changed.
+///
+/// Directories are watched rather than the files themselves so that files replaced by
+/// a rename (as editors and formatters commonly do) keep being reported.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    watched: HashSet<PathBuf>,
+    /// Directories asked for by the last [`Self::watch_files`], including any that failed.
+    wanted: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Result<Self> {
+        let (sender, events) = channel();
+        let watcher = notify::recommended_watcher(sender)?;
+        Ok(Self {
+            watcher,
+            events,
+            watched: HashSet::new(),
+            wanted: HashSet::new(),
+        })
+    }
+
+    /// Watch the parent directories of `files` and stop watching all other directories.
+    /// Directories that could not be watched are only tried again once the set changes.
+    pub fn watch_files<'a>(&mut self, files: impl Iterator<Item = &'a Path>) {
+        let dirs: HashSet<PathBuf> = files
+            .filter_map(|file| file.parent())
+            .map(|dir| match dir.as_os_str().is_empty() {
+                true => PathBuf::from("."),
+                false => dir.to_path_buf(),
+            })
+            .collect();
+        if dirs == self.wanted {
+            return;
+        }
+
+        for dir in self.watched.difference(&dirs) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        self.watched.retain(|dir| dirs.contains(dir));
+        for dir in &dirs {
+            if !self.watched.contains(dir)
+                && self.watcher.watch(dir, RecursiveMode::NonRecursive).is_ok()
+            {
+                self.watched.insert(dir.clone());
+            }
+        }
+        self.wanted = dirs;
+    }
+
+    /// Whether every directory asked for is being watched.
+    pub fn watches_all(&self) -> bool {
+        self.watched.len() == self.wanted.len()
+    }
+
+    /// Whether anything in the watched directories was created, modified or removed since
+    /// the last call.
+    pub fn has_changes(&self) -> bool {
+        self.events
+            .try_iter()
+            .filter_map(|event| event.ok())
+            .filter(|event| !event.kind.is_access())
+            .count()
+            > 0
+    }
+}